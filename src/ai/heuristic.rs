@@ -0,0 +1,120 @@
+use crate::board::*;
+use crate::gamestate::GameReferee;
+use crate::player::base_player::PlayerPiece;
+
+/// The highest score an evaluator may give a board, either way. A won or lost game scores more than this, so a sure win
+/// always beats a board that only looks good.
+pub const MAX_SCORE: i32 = 100_000_000;
+
+/// Scores boards that are not finished yet, for when the AI can't search all the way to the end of the game.
+pub trait BoardEvaluator {
+    /// Returns how good the board looks for `piece`. Positive scores are good for `piece`, negative scores are good for its opponents.
+    /// Scores go from `-MAX_SCORE` to `MAX_SCORE`.
+    fn evaluate(&self, board: &Board, referee: &GameReferee, piece: PlayerPiece) -> i32;
+}
+
+/// Counts the lines that a player can still win with, giving more weight to lines that are nearly full.
+///
+/// A "line" here is any stretch of `win_length` cells along a row, column or diagonal.
+/// A line is only open to a player if none of the opponent's pieces are in it.
+pub struct OpenLinesEvaluator;
+
+/// Lines that are missing this many pieces or more all score the same
+const WEIGHTED_PIECES: usize = 8;
+
+impl BoardEvaluator for OpenLinesEvaluator {
+    fn evaluate(&self, board: &Board, referee: &GameReferee, piece: PlayerPiece) -> i32 {
+        let rows = board.get_all_rows().into_iter().map(|row| row.to_vec());
+        let columns = board
+            .get_all_columns()
+            .into_iter()
+            .map(|column| column.to_vec());
+        let diagonals = board
+            .get_all_diagonals(Diagonal::Major)
+            .into_iter()
+            .chain(board.get_all_diagonals(Diagonal::Minor))
+            .map(|diagonal| diagonal.to_vec());
+
+        rows.chain(columns)
+            .chain(diagonals)
+            .map(|line| {
+                line.windows(referee.win_length())
+                    .map(|stretch| self.score_stretch(stretch, piece))
+                    .fold(0, i32::saturating_add)
+            })
+            .fold(0, i32::saturating_add)
+            .clamp(-MAX_SCORE, MAX_SCORE)
+    }
+}
+
+impl OpenLinesEvaluator {
+    fn score_stretch(&self, stretch: &[CellState], piece: PlayerPiece) -> i32 {
+        let own_pieces = stretch
+            .iter()
+            .filter(|&&cell| cell == CellState::Player(piece))
+            .count();
+        let other_pieces = stretch
+            .iter()
+            .filter(|&&cell| cell != CellState::Player(piece) && cell != CellState::Empty)
+            .count();
+
+        // Every extra piece in an open line makes it four times as valuable. The weight goes by how many pieces the line
+        // still needs, rather than how many it has, so it doesn't grow without bounds with the win length.
+        let weight = |pieces: usize| {
+            let missing = stretch.len() - pieces;
+            1 << (2 * WEIGHTED_PIECES.saturating_sub(missing))
+        };
+        match (own_pieces, other_pieces) {
+            (0, 0) => 0,
+            (own, 0) => weight(own),
+            (0, other) => -weight(other),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with(width: usize, pieces: &[(usize, u8)]) -> Board {
+        let mut board = Board::new(width, width).unwrap();
+        for &(index, piece) in pieces {
+            board.place(index, PlayerPiece::new(piece)).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn fuller_lines_score_higher() {
+        let referee = GameReferee::new(3);
+        let one = board_with(3, &[(0, 1)]);
+        let two = board_with(3, &[(0, 1), (1, 1)]);
+        let own = PlayerPiece::new(1);
+        let other = PlayerPiece::new(2);
+        assert!(OpenLinesEvaluator.evaluate(&one, &referee, own) > 0);
+        assert!(
+            OpenLinesEvaluator.evaluate(&two, &referee, own)
+                > OpenLinesEvaluator.evaluate(&one, &referee, own)
+        );
+        assert_eq!(
+            OpenLinesEvaluator.evaluate(&two, &referee, other),
+            -OpenLinesEvaluator.evaluate(&two, &referee, own)
+        );
+    }
+
+    #[test]
+    fn long_lines_stay_below_a_win() {
+        // Every row, column and the minor diagonal is one piece short of a win, on the biggest board there is
+        let width = BOARD_MAX_WIDTH;
+        let pieces: Vec<(usize, u8)> = (0..width * width)
+            .filter(|index| index / width != index % width)
+            .map(|index| (index, 1))
+            .collect();
+        let board = board_with(width, &pieces);
+        let score =
+            OpenLinesEvaluator.evaluate(&board, &GameReferee::new(width), PlayerPiece::new(1));
+        assert!(score > 0);
+        assert!(score <= MAX_SCORE);
+    }
+}
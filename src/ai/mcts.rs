@@ -0,0 +1,284 @@
+use std::cell::{Cell, RefCell};
+
+use crate::board::*;
+use crate::controller::*;
+use crate::player::base_player::PlayerPiece;
+use crate::rng::Rng;
+use crate::{GameReferee, GameState};
+
+/// An AI that picks moves with Monte Carlo Tree Search (MCTS).
+///
+/// Instead of trying to score every possible future, it plays lots of random games ("playouts") from the current board, and keeps track of which moves tend to lead to wins.
+/// The more promising a move looks, the more playouts it gets, so the AI spends most of its time on the moves that matter.
+/// This works on boards that are far too big for minimax to search deeply.
+pub struct AIMcts {
+    config: MctsConfig,
+    rng: RefCell<Rng>,
+    /// How many playouts the latest search ran through the move it picked, and how many of those it won
+    last_move_stats: Cell<(u32, f64)>,
+}
+
+/// Settings for the Monte Carlo Tree Search AI
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct MctsConfig {
+    /// How many random games to play out before picking a move. More playouts make the AI stronger, but slower.
+    /// The AI always plays out at least one game, since it has nothing to go on otherwise.
+    pub playouts: u32,
+    /// Seed for the random number generator, so that the AI makes the same choices every time. `None` picks a seed from the clock.
+    pub seed: Option<u64>,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            playouts: 20_000,
+            seed: None,
+        }
+    }
+}
+
+/// A position in the search tree. Nodes refer to each other by their index in the tree's node vector.
+struct Node {
+    parent: Option<usize>,
+    /// The move that led here from the parent, and the piece that made it. The root has none.
+    last_move: Option<(usize, PlayerPiece)>,
+    /// The piece that gets to move next from this position
+    piece_to_move: PlayerPiece,
+    children: Vec<usize>,
+    /// Moves we haven't made a child node for yet
+    untried_moves: Vec<usize>,
+    /// Whether the game is over at this position
+    is_terminal: bool,
+    visits: u32,
+    /// Playouts through this node won by the piece that made `last_move`. Draws count as half a win.
+    wins: f64,
+}
+
+/// How much UCT favours trying moves with few playouts over moves that have done well so far.
+/// The square root of 2 is the textbook choice.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+impl PlayerController for AIMcts {
+    fn handle_input(&self, gamestate: &GameState) -> Result<InputType, InputError> {
+        let best_move = self.find_best_move(gamestate);
+        Ok(InputType::Coord(best_move))
+    }
+    fn get_yes_no(&self) -> Result<bool, InputError> {
+        Ok(true)
+    }
+    fn describe_last_move(&self) -> Option<String> {
+        let (visits, wins) = self.last_move_stats.get();
+        Some(format!(
+            "The AI played out {} games. It chose a move that scored {:.0}% over the {visits} games that started with it, counting draws as half a win.",
+            self.config.playouts.max(1),
+            100.0 * wins / f64::from(visits.max(1))
+        ))
+    }
+}
+
+impl AIMcts {
+    pub fn new(config: MctsConfig) -> AIMcts {
+        AIMcts {
+            config,
+            rng: RefCell::new(Rng::from_seed_or_time(config.seed)),
+            last_move_stats: Cell::new((0, 0.0)),
+        }
+    }
+
+    /// Runs the configured number of playouts, each in four steps:
+    ///
+    /// 1. Selection: walk down the tree, picking the child with the best UCT score, until we reach a node with moves we haven't tried yet.
+    /// 2. Expansion: add a child node for one of those untried moves.
+    /// 3. Simulation: play random moves from there until the game is over.
+    /// 4. Backpropagation: walk back up to the root, recording the result in every node on the way.
+    ///
+    /// The move we pick in the end is the one that got the most playouts, since UCT gives the most playouts to the best-looking moves.
+    fn find_best_move(&self, game_state: &GameState) -> usize {
+        let next_piece = |piece: PlayerPiece| game_state.players().next_piece_after(piece);
+        let referee = game_state.referee();
+        let mut rng = self.rng.borrow_mut();
+
+        let mut tree = vec![Node {
+            parent: None,
+            last_move: None,
+            piece_to_move: game_state.piece_to_move(),
+            children: vec![],
+            untried_moves: self.get_candidate_moves(game_state.board()),
+            is_terminal: false,
+            visits: 0,
+            wins: 0.0,
+        }];
+
+        for _ in 0..self.config.playouts.max(1) {
+            let mut board = game_state.board().clone();
+
+            // 1. Selection
+            let mut node = 0;
+            while tree[node].untried_moves.is_empty() && !tree[node].children.is_empty() {
+                node = self.select_child(&tree, node);
+                let (index, piece) = tree[node].last_move.expect("Only the root has no move");
+                board.modify_at_cell(index, CellState::Player(piece));
+            }
+
+            // 2. Expansion
+            if !tree[node].is_terminal && !tree[node].untried_moves.is_empty() {
+                let untried_moves = &mut tree[node].untried_moves;
+                let index = untried_moves.swap_remove(rng.below(untried_moves.len()));
+                let piece = tree[node].piece_to_move;
+                board.modify_at_cell(index, CellState::Player(piece));
+                let is_terminal =
+                    referee.adjudicate_move(&board, index).is_some() || board.is_full();
+
+                tree.push(Node {
+                    parent: Some(node),
+                    last_move: Some((index, piece)),
+                    piece_to_move: next_piece(piece),
+                    children: vec![],
+                    untried_moves: if is_terminal {
+                        vec![]
+                    } else {
+                        self.get_candidate_moves(&board)
+                    },
+                    is_terminal,
+                    visits: 0,
+                    wins: 0.0,
+                });
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                node = child;
+            }
+
+            // 3. Simulation
+            let winner = match tree[node].last_move {
+                Some((index, piece)) if tree[node].is_terminal => referee
+                    .adjudicate_move(&board, index)
+                    .map(|_| piece),
+                _ => self.play_out(
+                    referee,
+                    &mut board,
+                    tree[node].piece_to_move,
+                    &next_piece,
+                    &mut rng,
+                ),
+            };
+
+            // 4. Backpropagation
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let tree_node = &mut tree[index];
+                tree_node.visits += 1;
+                tree_node.wins += match (winner, tree_node.last_move) {
+                    (None, _) => 0.5,
+                    (Some(winning_piece), Some((_, piece))) if winning_piece == piece => 1.0,
+                    _ => 0.0,
+                };
+                current = tree_node.parent;
+            }
+        }
+
+        let best_child = tree[0]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child| tree[child].visits)
+            .expect("There is at least one playout, and so at least one child of the root");
+        self.last_move_stats
+            .set((tree[best_child].visits, tree[best_child].wins));
+        tree[best_child]
+            .last_move
+            .map(|(index, _)| index)
+            .expect("Only the root has no move")
+    }
+
+    /// Picks the child with the highest UCT (Upper Confidence bounds applied to Trees) score.
+    ///
+    /// The score is the child's win rate, plus a bonus that grows for children that have had few playouts compared to their parent.
+    /// That way we mostly follow the moves that have done well, but still give the others a chance to prove themselves.
+    fn select_child(&self, tree: &[Node], node: usize) -> usize {
+        let log_parent_visits = f64::from(tree[node].visits).ln();
+        let uct = |child: usize| {
+            let visits = f64::from(tree[child].visits);
+            tree[child].wins / visits + EXPLORATION * (log_parent_visits / visits).sqrt()
+        };
+        tree[node]
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+            .expect("Only called on nodes with children")
+    }
+
+    /// Plays random moves until the game is over, and returns the winning piece, if any.
+    fn play_out(
+        &self,
+        referee: &GameReferee,
+        board: &mut Board,
+        mut piece_to_move: PlayerPiece,
+        next_piece: &impl Fn(PlayerPiece) -> PlayerPiece,
+        rng: &mut Rng,
+    ) -> Option<PlayerPiece> {
+        let mut empty_cells = board.get_indices_of_empty_cells();
+        while !empty_cells.is_empty() {
+            let index = empty_cells.swap_remove(rng.below(empty_cells.len()));
+            board.modify_at_cell(index, CellState::Player(piece_to_move));
+            if referee.adjudicate_move(board, index).is_some() {
+                return Some(piece_to_move);
+            }
+            piece_to_move = next_piece(piece_to_move);
+        }
+        None
+    }
+
+    /// The moves we add to the tree. On big boards, we stick to cells near the pieces already on the board, like the minimax AI does.
+    fn get_candidate_moves(&self, board: &Board) -> Vec<usize> {
+        const SMALL_BOARD_SIZE: usize = 16;
+
+        if board.len() <= SMALL_BOARD_SIZE {
+            board.get_indices_of_empty_cells()
+        } else {
+            board.get_indices_of_empty_cells_near_pieces()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::base_player::Player;
+    use crate::player::playerlist::PlayerList;
+
+    /// Lets one AI play both sides of a game on a 4x4 board, and returns the moves it made
+    fn play_both_sides(playouts: u32, seed: u64) -> Vec<usize> {
+        let ai = AIMcts::new(MctsConfig {
+            playouts,
+            seed: Some(seed),
+        });
+        let player = |symbol: char, piece: u8| {
+            Player::local(&symbol.to_string(), symbol, PlayerPiece::new(piece))
+        };
+        let mut game = GameState::new(
+            Board::new(4, 4).unwrap(),
+            GameReferee::new(3),
+            PlayerList::new(player('x', 1), player('o', 2)),
+        );
+        while game.outcome().is_none() {
+            game.place_piece(ai.find_best_move(&game)).unwrap();
+        }
+        game.moves()
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_game() {
+        let games: Vec<Vec<usize>> = (0..5).map(|seed| play_both_sides(500, seed)).collect();
+        for (seed, game) in games.iter().enumerate() {
+            assert_eq!(*game, play_both_sides(500, seed as u64), "seed {seed}");
+        }
+        // Otherwise the seed wouldn't be doing anything
+        assert!(games.iter().any(|game| *game != games[0]));
+    }
+
+    #[test]
+    fn plays_a_move_without_any_playouts() {
+        assert!(!play_both_sides(0, 0).is_empty());
+    }
+}
@@ -1,62 +1,265 @@
+use std::cell::{Cell, RefCell};
 use std::cmp;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use crate::ai::heuristic::{self, BoardEvaluator};
 use crate::board::*;
 use crate::controller::*;
-use crate::GameState;
+use crate::player::base_player::PlayerPiece;
+use crate::rng::Rng;
+use crate::{GameReferee, GameState};
 
-pub struct AIMinimax {}
+// Winning has to beat any score the evaluator can come up with, however deep in the tree the win is
+const WINNING_MOVE_SCORE: i32 = 1_000_000_000;
+const LOSING_MOVE_SCORE: i32 = -1_000_000_000;
+const DRAW_MOVE_SCORE: i32 = 0;
+const _: () =
+    assert!(WINNING_MOVE_SCORE - (BOARD_MAX_WIDTH * BOARD_MAX_WIDTH) as i32 > heuristic::MAX_SCORE);
 
-/// Represents a Board State and the index of the move that made it possible
-struct BoardAfterMove((Board, usize));
+pub struct AIMinimax {
+    config: MinimaxConfig,
+    /// Scores the boards where the search has to stop before the game is over
+    evaluator: Box<dyn BoardEvaluator>,
+    /// How many board positions the latest search looked at
+    nodes_visited: Cell<u64>,
+    /// How many moves ahead the search in progress looks
+    max_depth: Cell<usize>,
+    /// How many moves ahead the latest search that ran to completion looked
+    completed_depth: Cell<usize>,
+    /// When the search in progress has to give up, if it has a time budget
+    deadline: Cell<Option<Instant>>,
+    /// Set once the search in progress has run past its deadline
+    timed_out: Cell<bool>,
+    /// Scores of positions we have already searched during this move, keyed on `Board::canonical_hash`.
+    /// The same position can be reached through different move orders, and a lot of positions are mirror images of each other.
+    transposition_table: RefCell<HashMap<u64, TableEntry>>,
+    /// Decides when the AI blunders, and which move it blunders into
+    rng: RefCell<Rng>,
+    /// Whether the latest move was a blunder rather than the result of a search
+    blundered: Cell<bool>,
+    /// How many times the latest search looked for a position in the transposition table
+    table_lookups: Cell<u64>,
+    /// How many of those lookups found a score it could use
+    table_hits: Cell<u64>,
+}
+
+/// What we remember about a position we have already searched
+#[derive(Clone, Copy)]
+struct TableEntry {
+    score: i32,
+    /// How many moves ahead of this position we looked. A score from a shallower search than the one we're doing is not good enough.
+    remaining_depth: usize,
+    bound: Bound,
+}
+
+/// Alpha/beta pruning means a score is not always exact. If a search is cut short, we only know that the real score is at least, or at most, this good.
+#[derive(Clone, Copy)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// How long the minimax AI thinks about each move, unless it is told otherwise
+pub const DEFAULT_TIME_BUDGET: Duration = Duration::from_millis(500);
+
+/// Settings for the minimax AI
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct MinimaxConfig {
+    /// How many moves ahead the AI looks, counting its own move. `None` lets the AI pick a depth that suits the board size.
+    pub max_depth: Option<usize>,
+    /// How long the AI may think about each move. With a time budget, the AI searches one move deeper at a time until the time runs out.
+    pub time_budget: Option<Duration>,
+    /// The chance, in percent, that the AI ignores its search and makes a random move instead
+    pub blunder_percent: u8,
+    /// Seed for the random number generator that decides when and how to blunder. `None` picks a seed from the clock.
+    pub seed: Option<u64>,
+}
 
-impl std::ops::Deref for BoardAfterMove {
-    type Target = Board;
-    fn deref(&self) -> &Self::Target {
-        &self.0 .0
+impl Default for MinimaxConfig {
+    fn default() -> Self {
+        MinimaxConfig {
+            max_depth: None,
+            time_budget: Some(DEFAULT_TIME_BUDGET),
+            blunder_percent: 0,
+            seed: None,
+        }
     }
 }
 
+/// The range of scores that are still interesting to the players further up the search tree.
+///
+/// `alpha` is the best score the maximizer (the AI) is already guaranteed, and `beta` is the best score the minimizer (its opponent) is guaranteed.
+#[derive(Clone, Copy)]
+struct SearchWindow {
+    alpha: i32,
+    beta: i32,
+}
+
 impl PlayerController for AIMinimax {
     fn handle_input(&self, gamestate: &GameState) -> Result<InputType, InputError> {
         let best_move = self.find_best_move(gamestate);
-        Ok(InputType::Coord(best_move))
+        Ok(InputType::Coord(self.maybe_blunder(gamestate.board(), best_move)))
     }
     fn get_yes_no(&self) -> Result<bool, InputError> {
         Ok(true)
     }
+    fn describe_last_move(&self) -> Option<String> {
+        if self.blundered.get() {
+            return Some("The AI wasn't paying attention, and made a random move.".to_string());
+        }
+        let hit_rate = 100 * self.table_hits.get() / cmp::max(1, self.table_lookups.get());
+        Some(format!(
+            "The AI looked at {} positions, {} moves ahead, before choosing. Cache hit rate: {hit_rate}% ({} of {} lookups).",
+            self.nodes_visited.get(),
+            self.completed_depth.get(),
+            self.table_hits.get(),
+            self.table_lookups.get()
+        ))
+    }
 }
 
 impl AIMinimax {
-    pub fn new() -> AIMinimax {
-        AIMinimax {}
+    pub fn new(config: MinimaxConfig, evaluator: Box<dyn BoardEvaluator>) -> AIMinimax {
+        AIMinimax {
+            config,
+            evaluator,
+            nodes_visited: Cell::new(0),
+            max_depth: Cell::new(0),
+            completed_depth: Cell::new(0),
+            deadline: Cell::new(None),
+            timed_out: Cell::new(false),
+            transposition_table: RefCell::new(HashMap::new()),
+            rng: RefCell::new(Rng::from_seed_or_time(config.seed)),
+            blundered: Cell::new(false),
+            table_lookups: Cell::new(0),
+            table_hits: Cell::new(0),
+        }
+    }
+
+    /// How many moves ahead to look on this board. Bigger boards have more moves to choose from at every step, so we can't look as far ahead.
+    ///
+    /// If the AI has a time budget but no maximum depth, the clock decides how far ahead it gets to look.
+    fn search_depth(&self, board: &Board) -> usize {
+        match (self.config.max_depth, self.config.time_budget) {
+            (Some(max_depth), _) => max_depth,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => match board.len() {
+                0..=9 => usize::MAX,
+                10..=16 => 8,
+                17..=49 => 6,
+                _ => 4,
+            },
+        }
+    }
+
+    /// Every so often, depending on `MinimaxConfig::blunder_percent`, swaps the best move for a random one.
+    ///
+    /// This gives weaker AIs a human touch: they usually play well, but now and then overlook something.
+    fn maybe_blunder(&self, board: &Board, best_move: usize) -> usize {
+        let mut rng = self.rng.borrow_mut();
+        let blunders = rng.below(100) < usize::from(self.config.blunder_percent);
+        self.blundered.set(blunders);
+        if blunders {
+            let empty_cells = board.get_indices_of_empty_cells();
+            empty_cells[rng.below(empty_cells.len())]
+        } else {
+            best_move
+        }
     }
 
     /// This is the entry point for the minimax algorithm.
     ///
     /// Find best move initiates a sequence of minimax searches down through a tree-graph of possible game states, from the current game state.
     ///
-    /// For each such possible search through the gamestate tree, the algorithm will eventually yield a "score" for this given move. This score is calculated when the minimax algorithm reaches a leaf node/terminal node. A victory for the AI will represent a very high score of 10; a draw is scored as 0, and a loss (to the human player) represents a -10. In addition, we subtract the "depth" of the tree from this score, so that a winning move that is 4 steps away is scored as 6, whereas a winning move this very turn is scored as a 10. That way, the AI will prioritze the quickest path to victory.
+    /// For each such possible search through the gamestate tree, the algorithm will eventually yield a "score" for this given move. This score is calculated when the minimax algorithm reaches a leaf node/terminal node. A victory for the AI will represent a very high score; a draw is scored as 0, and a loss (to the opponent) represents a very low score. In addition, we subtract the "depth" of the tree from this score, so that a winning move that is 4 steps away is scored lower than a winning move this very turn. That way, the AI will prioritze the quickest path to victory.
+    ///
+    /// On larger boards we can't search all the way to the end of the game. Once we reach the maximum search depth, the `BoardEvaluator` guesses how good the board is instead.
+    ///
+    /// If the AI has a time budget, we search one move deeper at a time ("iterative deepening"), and stop when the time is up.
+    /// We then go with the best move from the deepest search that was completed in time.
     ///
     /// The job of the algorithm (and this function) is to return the move that will in the quickest way possible lead to the highest score
     fn find_best_move(&self, game_state: &GameState) -> usize {
-        let possible_moves = game_state.board().get_indices_of_empty_cells();
+        self.nodes_visited.set(0);
+        self.timed_out.set(false);
+        self.table_lookups.set(0);
+        self.table_hits.set(0);
+        // Scores count how many moves away a win is from the current board, so they don't carry over to the next move
+        self.transposition_table.borrow_mut().clear();
+        // There is no point in looking further ahead than the number of moves left in the game
+        let moves_left = game_state.board().get_indices_of_empty_cells().len();
+        let depth_limit = cmp::min(self.search_depth(game_state.board()), moves_left);
+
+        let Some(time_budget) = self.config.time_budget else {
+            self.deadline.set(None);
+            self.completed_depth.set(depth_limit);
+            return self.search_to_depth(game_state, depth_limit, None);
+        };
+
+        self.deadline.set(Some(Instant::now() + time_budget));
+        let mut best_move = None;
+        for depth in 1..=depth_limit {
+            let best_move_at_depth = self.search_to_depth(game_state, depth, best_move);
+            if self.timed_out.get() {
+                break;
+            }
+            best_move = Some(best_move_at_depth);
+            self.completed_depth.set(depth);
+        }
+
+        // Even the shallowest search can run out of time on a huge board. Any move is better than none.
+        best_move.unwrap_or_else(|| {
+            self.get_candidate_moves(game_state.board(), depth_limit)[0]
+        })
+    }
+
+    /// Runs a single search that looks `max_depth` moves ahead, and returns the best move it found.
+    ///
+    /// `first_move_to_try` is usually the best move from a shallower search. It is likely to still be good, so trying it first makes the pruning more effective.
+    fn search_to_depth(
+        &self,
+        game_state: &GameState,
+        max_depth: usize,
+        first_move_to_try: Option<usize>,
+    ) -> usize {
+        self.max_depth.set(max_depth);
+        let mut possible_moves = self.get_candidate_moves(game_state.board(), max_depth);
+        if let Some(first_move) = first_move_to_try
+            && let Some(position) = possible_moves.iter().position(|&m| m == first_move)
+        {
+            possible_moves[..=position].rotate_right(1);
+        }
         /*
         NOTE: We are at this point assuming the board is not full, quite simply due to the main game logic. See src/gamestate/mod.rs.
         The alternative is to wrap the return value of this function in an Option. That would require some changes to the core data flow.
         */
         let mut best_move = possible_moves[0];
-        let mut best_score = i8::MIN;
+        let mut best_score = i32::MIN;
 
         let mut temporary_board = game_state.board().clone();
 
         // The idea here is to temporarily modify a board, perform the minimax calculation, then "reset" that board. In that way, we can repeatedly use the same temporary_board and not clone clone clone.
+        // Apart from the first move to try, we deliberately try the moves in plain index order here, so that ties between equally good moves are broken the same way every time.
         for &move_index in &possible_moves {
             temporary_board.modify_at_cell(
                 move_index,
-                CellState::Player(game_state.players().get_ai_player_piece().unwrap()),
+                CellState::Player(game_state.piece_to_move()),
             );
 
-            let score = self.minimax(game_state, &temporary_board, 0, false);
+            // Only a strictly better score can change our mind, so the best score so far is our alpha
+            let score = self.minimax(
+                game_state,
+                &mut temporary_board,
+                move_index,
+                0,
+                SearchWindow {
+                    alpha: best_score,
+                    beta: i32::MAX,
+                },
+                false,
+            );
 
             if score > best_score {
                 best_score = score;
@@ -64,80 +267,414 @@ impl AIMinimax {
             }
             // Resetting the board so we don't have to clone multiple times.
             temporary_board.modify_at_cell(move_index, CellState::Empty);
+
+            if self.timed_out.get() {
+                break;
+            }
         }
 
         best_move
     }
 
+    /// Scores the board from the point of view of the player the AI is playing for, using alpha/beta pruning.
+    ///
+    /// `last_move` is the cell that was just filled in. Since the board had no winner before that move, it is the only place a new winning line can show up.
+    ///
+    /// As soon as alpha meets beta, the remaining moves from this board can't possibly be chosen by whoever moved before us, so we stop looking at them.
     fn minimax(
         &self,
         game_state: &GameState,
-        board_to_analyze: &Board,
-        depth: i8,
+        board_to_analyze: &mut Board,
+        last_move: usize,
+        depth: usize,
+        mut window: SearchWindow,
         is_maximizer: bool,
-    ) -> i8 {
-        const WINNING_MOVE_SCORE: i8 = 10;
-        const LOSING_MOVE_SCORE: i8 = -10;
-        const DRAW_MOVE_SCORE: i8 = 0;
+    ) -> i32 {
+        self.nodes_visited.set(self.nodes_visited.get() + 1);
+        if self.is_out_of_time() {
+            // The score doesn't matter, since the caller throws away the results of a search that ran out of time
+            return 0;
+        }
 
-        // NOTE: We unwrap because we strongly assume that there is an AI piece at this point
-        let ai_player_piece = game_state.players().get_ai_player_piece().unwrap();
-        let local_human_player_piece = game_state.players().get_local_human_player_piece();
+        // We are searching on behalf of whoever's turn it is on the real board
+        let own_piece = game_state.piece_to_move();
+        let opponent_piece = game_state.players().next_piece_after(own_piece);
 
-        let winner = game_state.referee().adjudicate(board_to_analyze);
+        let winner = game_state
+            .referee()
+            .adjudicate_move(board_to_analyze, last_move);
         if winner.is_some() || board_to_analyze.is_full() {
             if let Some(winning_piece) = winner {
-                if winning_piece == ai_player_piece {
-                    return WINNING_MOVE_SCORE - depth;
+                if winning_piece == own_piece {
+                    return WINNING_MOVE_SCORE - depth as i32;
                 } else {
-                    return LOSING_MOVE_SCORE + depth;
+                    return LOSING_MOVE_SCORE + depth as i32;
                 }
             }
             return DRAW_MOVE_SCORE;
         }
 
-        if is_maximizer {
-            let mut best = i8::MIN;
-            let possible_moves = self.get_possible_board_states_from_current_board(
+        // The AI's own move at the root of the tree counts as the first move we look ahead
+        let remaining_depth = self.max_depth.get().saturating_sub(depth + 1);
+        let hash = board_to_analyze.canonical_hash();
+        let original_window = window;
+        if let Some(entry) = self.look_up(hash, remaining_depth) {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => window.alpha = cmp::max(window.alpha, entry.score),
+                Bound::Upper => window.beta = cmp::min(window.beta, entry.score),
+            }
+            if window.alpha >= window.beta {
+                return entry.score;
+            }
+        }
+
+        if remaining_depth == 0 {
+            let score =
+                self.evaluator
+                    .evaluate(board_to_analyze, game_state.referee(), own_piece);
+            self.remember(hash, score, remaining_depth, Bound::Exact);
+            return score;
+        }
+
+        let (piece_to_move, piece_to_move_next, mut best) = if is_maximizer {
+            (own_piece, opponent_piece, i32::MIN)
+        } else {
+            (opponent_piece, own_piece, i32::MAX)
+        };
+
+        for possible_move in self.get_ordered_moves(
+            game_state.referee(),
+            board_to_analyze,
+            remaining_depth + 1,
+            piece_to_move,
+            piece_to_move_next,
+        ) {
+            board_to_analyze.modify_at_cell(possible_move, CellState::Player(piece_to_move));
+            let score = self.minimax(
+                game_state,
                 board_to_analyze,
-                CellState::Player(ai_player_piece),
+                possible_move,
+                depth + 1,
+                window,
+                !is_maximizer,
             );
-            for new_board_state in possible_moves {
-                let new_best = self.minimax(game_state, &new_board_state, depth + 1, false);
+            board_to_analyze.modify_at_cell(possible_move, CellState::Empty);
 
-                best = cmp::max(best, new_best);
+            if is_maximizer {
+                best = cmp::max(best, score);
+                window.alpha = cmp::max(window.alpha, best);
+            } else {
+                best = cmp::min(best, score);
+                window.beta = cmp::min(window.beta, best);
+            }
+            if window.alpha >= window.beta {
+                break;
             }
-            best
+        }
+
+        let bound = if best <= original_window.alpha {
+            Bound::Upper
+        } else if best >= original_window.beta {
+            Bound::Lower
         } else {
-            let mut best = i8::MAX;
-            let possible_moves = self.get_possible_board_states_from_current_board(
-                board_to_analyze,
-                CellState::Player(local_human_player_piece),
-            );
+            Bound::Exact
+        };
+        self.remember(hash, best, remaining_depth, bound);
+        best
+    }
+
+    /// Looks for a score for the position in the transposition table, from a search that looked at least `remaining_depth` moves ahead
+    fn look_up(&self, hash: u64, remaining_depth: usize) -> Option<TableEntry> {
+        self.table_lookups.set(self.table_lookups.get() + 1);
+        let entry = self
+            .transposition_table
+            .borrow()
+            .get(&hash)
+            .copied()
+            .filter(|entry| entry.remaining_depth >= remaining_depth);
+        if entry.is_some() {
+            self.table_hits.set(self.table_hits.get() + 1);
+        }
+        entry
+    }
 
-            for new_board_state in possible_moves {
-                best = cmp::min(
-                    best,
-                    self.minimax(game_state, &new_board_state, depth + 1, true),
+    fn remember(&self, hash: u64, score: i32, remaining_depth: usize, bound: Bound) {
+        // A search that ran out of time returns made-up scores, which we must not remember
+        if self.timed_out.get() {
+            return;
+        }
+        self.transposition_table.borrow_mut().insert(
+            hash,
+            TableEntry {
+                score,
+                remaining_depth,
+                bound,
+            },
+        );
+    }
+
+    /// Checks whether the search in progress has run past its deadline.
+    ///
+    /// Looking at the clock is slow compared to looking at a board, so we only do it every so often.
+    fn is_out_of_time(&self) -> bool {
+        const NODES_BETWEEN_CLOCK_CHECKS: u64 = 1024;
+
+        if !self.timed_out.get()
+            && self
+                .nodes_visited
+                .get()
+                .is_multiple_of(NODES_BETWEEN_CLOCK_CHECKS)
+            && let Some(deadline) = self.deadline.get()
+        {
+            self.timed_out.set(Instant::now() >= deadline);
+        }
+        self.timed_out.get()
+    }
+
+    /// Returns the empty cells on the board, with the most promising moves first.
+    ///
+    /// Moves that win on the spot come first, followed by moves that block the opponent from winning on their next move.
+    /// The rest are sorted with the cells closest to the centre first, since central cells are part of more lines than cells near the edges.
+    /// Looking at good moves first lets alpha/beta pruning discard the rest of the moves sooner.
+    fn get_ordered_moves(
+        &self,
+        referee: &GameReferee,
+        board: &mut Board,
+        remaining_depth: usize,
+        piece_to_move: PlayerPiece,
+        opponent_piece: PlayerPiece,
+    ) -> Vec<usize> {
+        // Distances are doubled, so that the centre of an even-sized board stays a whole number
+        let centre_x = board.width() as isize - 1;
+        let centre_y = board.height() as isize - 1;
+        let mut moves: Vec<(usize, (u8, isize))> = self
+            .get_candidate_moves(board, remaining_depth)
+            .into_iter()
+            .map(|index| {
+                let urgency = if self.completes_line(referee, board, index, piece_to_move) {
+                    0
+                } else if self.completes_line(referee, board, index, opponent_piece) {
+                    1
+                } else {
+                    2
+                };
+                let x = 2 * (index % board.width()) as isize;
+                let y = 2 * (index / board.width()) as isize;
+                (
+                    index,
+                    (urgency, (x - centre_x).abs() + (y - centre_y).abs()),
                 )
+            })
+            .collect();
+        moves.sort_by_key(|&(_, priority)| priority);
+        moves.into_iter().map(|(index, _)| index).collect()
+    }
+
+    /// Checks whether placing `piece` at `index` would give that piece a winning line
+    fn completes_line(
+        &self,
+        referee: &GameReferee,
+        board: &mut Board,
+        index: usize,
+        piece: PlayerPiece,
+    ) -> bool {
+        board.modify_at_cell(index, CellState::Player(piece));
+        let wins = referee.adjudicate_move(board, index).is_some();
+        board.modify_at_cell(index, CellState::Empty);
+        wins
+    }
+
+    /// Returns the moves worth considering, in plain index order.
+    ///
+    /// If we can search all the way to the end of the game, every empty cell is a candidate.
+    /// Otherwise, we only consider cells next to a piece that is already on the board, since moves far away from the action are rarely any good.
+    /// This keeps the number of moves manageable on big boards like Gomoku.
+    fn get_candidate_moves(&self, board: &Board, remaining_depth: usize) -> Vec<usize> {
+        let empty_cells = board.get_indices_of_empty_cells();
+        if remaining_depth >= empty_cells.len() {
+            return empty_cells;
+        }
+
+        board.get_indices_of_empty_cells_near_pieces()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::heuristic::OpenLinesEvaluator;
+    use crate::player::base_player::Player;
+    use crate::player::playerlist::PlayerList;
+    use std::collections::HashSet;
+
+    /// Minimax the way it was before pruning: every move, all the way to the end of the game.
+    /// Returns the score of the board for the first of `pieces`, and how many positions plain minimax looks at to get it.
+    ///
+    /// Searching the same position twice gives the same answer, so `known` remembers them to keep the test quick.
+    /// Positions found there still count as all of the positions plain minimax would have looked at.
+    fn plain_minimax(
+        referee: &GameReferee,
+        board: &mut Board,
+        last_move: usize,
+        depth: usize,
+        pieces: (PlayerPiece, PlayerPiece),
+        is_maximizer: bool,
+        known: &mut HashMap<u32, (i32, u64)>,
+    ) -> (i32, u64) {
+        let (own_piece, opponent_piece) = pieces;
+        match referee.adjudicate_move(board, last_move) {
+            Some(winner) if winner == own_piece => return (WINNING_MOVE_SCORE - depth as i32, 1),
+            Some(_) => return (LOSING_MOVE_SCORE + depth as i32, 1),
+            None if board.is_full() => return (DRAW_MOVE_SCORE, 1),
+            None => {}
+        }
+        // Each cell is a digit in base 3
+        let key = (0..board.len()).fold(0, |key, index| {
+            let cell = board.get_cell(
+                (index / board.width()) as isize,
+                (index % board.width()) as isize,
+            );
+            let digit = match cell {
+                Some(CellState::Player(piece)) => u32::from(*piece),
+                _ => 0,
+            };
+            key * 3 + digit
+        });
+        if let Some(&known) = known.get(&key) {
+            return known;
+        }
+
+        let piece_to_move = if is_maximizer {
+            own_piece
+        } else {
+            opponent_piece
+        };
+        let mut best = if is_maximizer { i32::MIN } else { i32::MAX };
+        let mut nodes = 1;
+        for index in board.get_indices_of_empty_cells() {
+            board.modify_at_cell(index, CellState::Player(piece_to_move));
+            let (score, child_nodes) = plain_minimax(
+                referee,
+                board,
+                index,
+                depth + 1,
+                pieces,
+                !is_maximizer,
+                known,
+            );
+            board.modify_at_cell(index, CellState::Empty);
+            nodes += child_nodes;
+            best = if is_maximizer {
+                cmp::max(best, score)
+            } else {
+                cmp::min(best, score)
+            };
+        }
+        known.insert(key, (best, nodes));
+        (best, nodes)
+    }
+
+    /// The move plain minimax picks, the first of the best ones in index order, and how many positions it looks at
+    fn plain_best_move(game: &GameState) -> (usize, u64) {
+        let mut board = game.board().clone();
+        let own_piece = game.piece_to_move();
+        let pieces = (own_piece, game.players().next_piece_after(own_piece));
+        let mut known = HashMap::new();
+        let mut nodes = 0;
+        let mut best = (usize::MAX, i32::MIN);
+        for index in board.get_indices_of_empty_cells() {
+            board.modify_at_cell(index, CellState::Player(own_piece));
+            let (score, child_nodes) = plain_minimax(
+                game.referee(),
+                &mut board,
+                index,
+                0,
+                pieces,
+                false,
+                &mut known,
+            );
+            board.modify_at_cell(index, CellState::Empty);
+            nodes += child_nodes;
+            if score > best.1 {
+                best = (index, score);
             }
-            best
         }
+        (best.0, nodes)
     }
 
-    fn get_possible_board_states_from_current_board(
-        &self,
-        current_board: &Board,
-        player_to_move: CellState,
-    ) -> Vec<BoardAfterMove> {
-        let mut possible_board_states: Vec<BoardAfterMove> = vec![];
-        let all_possible_moves = current_board.get_indices_of_empty_cells();
-        for possible_move in all_possible_moves {
-            let mut new_state = current_board.clone();
-            new_state.modify_at_cell(possible_move, player_to_move);
-            possible_board_states.push(BoardAfterMove((new_state, possible_move)));
-        }
-
-        possible_board_states
+    /// Every unfinished 3x3 board that can come up in a game, as the moves that lead there
+    fn reachable_positions() -> Vec<Vec<usize>> {
+        fn explore(
+            game: &mut GameState,
+            moves: &mut Vec<usize>,
+            seen: &mut HashSet<Vec<usize>>,
+            positions: &mut Vec<Vec<usize>>,
+        ) {
+            // The same board comes up through different move orders, with the same pieces on the same cells
+            let mut pieces: Vec<usize> = moves.iter().step_by(2).copied().collect();
+            let mut other_pieces: Vec<usize> = moves.iter().skip(1).step_by(2).copied().collect();
+            pieces.sort();
+            other_pieces.sort();
+            pieces.push(usize::MAX);
+            pieces.extend(other_pieces);
+            if game.outcome().is_some() || !seen.insert(pieces) {
+                return;
+            }
+            positions.push(moves.clone());
+            for index in game.board().get_indices_of_empty_cells() {
+                moves.push(index);
+                game.replay(moves).unwrap();
+                explore(game, moves, seen, positions);
+                moves.pop();
+            }
+            game.replay(moves).unwrap();
+        }
+
+        let mut game = game_on_3x3();
+        let mut positions = Vec::new();
+        explore(&mut game, &mut vec![], &mut HashSet::new(), &mut positions);
+        positions
+    }
+
+    fn game_on_3x3() -> GameState {
+        let player = |symbol: char, piece: u8| {
+            Player::local(&symbol.to_string(), symbol, PlayerPiece::new(piece))
+        };
+        GameState::new(
+            Board::new(3, 3).unwrap(),
+            GameReferee::new(3),
+            PlayerList::new(player('x', 1), player('o', 2)),
+        )
+    }
+
+    #[test]
+    fn pruning_picks_the_same_moves_as_plain_minimax_with_fewer_nodes() {
+        let ai = AIMinimax::new(
+            MinimaxConfig {
+                max_depth: None,
+                time_budget: None,
+                blunder_percent: 0,
+                seed: Some(0),
+            },
+            Box::new(OpenLinesEvaluator),
+        );
+        let positions = reachable_positions();
+        assert_eq!(positions.len(), 4520);
+
+        let mut game = game_on_3x3();
+        let (mut pruned_nodes, mut plain_nodes) = (0, 0);
+        for moves in &positions {
+            game.replay(moves).unwrap();
+            let (plain_move, nodes) = plain_best_move(&game);
+            assert_eq!(ai.find_best_move(&game), plain_move, "after {moves:?}");
+            assert!(ai.nodes_visited.get() <= nodes, "after {moves:?}");
+            pruned_nodes += ai.nodes_visited.get();
+            plain_nodes += nodes;
+        }
+        assert!(
+            plain_nodes >= 20 * pruned_nodes,
+            "{pruned_nodes} positions with pruning, {plain_nodes} without"
+        );
     }
 }
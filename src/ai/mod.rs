@@ -1,6 +1,130 @@
+pub mod heuristic;
+pub mod mcts;
 pub mod minimax;
 
+use crate::ai::mcts::MctsConfig;
+use crate::ai::minimax::{DEFAULT_TIME_BUDGET, MinimaxConfig};
+use serde::Deserialize;
+use std::time::Duration;
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum AIStrategy {
-    Minimax,
+    Minimax(MinimaxConfig),
+    Mcts(MctsConfig),
+}
+
+/// How hard the AI should be to beat. Each level is a preset for the AI settings.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Perfect,
+}
+
+impl Difficulty {
+    /// Weaker minimax AIs look fewer moves ahead, and make more random moves. They all think for `think_time` per move,
+    /// or for the default time if it is None.
+    pub fn minimax_config(&self, seed: Option<u64>, think_time: Option<Duration>) -> MinimaxConfig {
+        let (max_depth, blunder_percent) = match self {
+            Difficulty::Easy => (Some(1), 40),
+            Difficulty::Medium => (Some(2), 20),
+            Difficulty::Hard => (Some(4), 5),
+            Difficulty::Perfect => (None, 0),
+        };
+        MinimaxConfig {
+            max_depth,
+            time_budget: Some(think_time.unwrap_or(DEFAULT_TIME_BUDGET)),
+            blunder_percent,
+            seed,
+        }
+    }
+
+    /// Weaker Monte Carlo AIs play out fewer games before picking a move
+    pub fn mcts_config(&self, seed: Option<u64>) -> MctsConfig {
+        let playouts = match self {
+            Difficulty::Easy => 50,
+            Difficulty::Medium => 500,
+            Difficulty::Hard => 5_000,
+            Difficulty::Perfect => MctsConfig::default().playouts,
+        };
+        MctsConfig { playouts, seed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::controller::InputType;
+    use crate::player::base_player::{Player, PlayerPiece};
+    use crate::player::playerlist::PlayerList;
+    use crate::{GameReferee, GameState};
+
+    const LEVELS: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Perfect,
+    ];
+
+    /// Plays a 3x3 game between two AIs with the same settings, and returns the moves they made
+    fn play(strategy: AIStrategy) -> Vec<usize> {
+        let player = |symbol: char, piece: u8| {
+            Player::ai(
+                &symbol.to_string(),
+                symbol,
+                PlayerPiece::new(piece),
+                strategy,
+            )
+        };
+        let mut game = GameState::new(
+            Board::new(3, 3).unwrap(),
+            GameReferee::new(3),
+            PlayerList::new(player('x', 1), player('o', 2)),
+        );
+        while game.outcome().is_none() {
+            match game.current_player().controller.handle_input(&game) {
+                Ok(InputType::Coord(index)) => game.place_piece(index).unwrap(),
+                _ => panic!("The AI should only ever suggest moves"),
+            }
+        }
+        game.moves()
+    }
+
+    /// A search that runs out of time stops wherever it got to, so these get all the time they need
+    fn minimax(difficulty: Difficulty, seed: u64) -> AIStrategy {
+        AIStrategy::Minimax(difficulty.minimax_config(Some(seed), Some(Duration::from_secs(60))))
+    }
+
+    fn mcts(difficulty: Difficulty, seed: u64) -> AIStrategy {
+        AIStrategy::Mcts(difficulty.mcts_config(Some(seed)))
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_game_at_every_level() {
+        for (seed, difficulty) in LEVELS.into_iter().enumerate() {
+            let seed = seed as u64;
+            let message = format!("{difficulty:?} with seed {seed}");
+            assert_eq!(
+                play(minimax(difficulty, seed)),
+                play(minimax(difficulty, seed)),
+                "{message}"
+            );
+            assert_eq!(
+                play(mcts(difficulty, seed)),
+                play(mcts(difficulty, seed)),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn blunders_depend_on_the_seed() {
+        let games: Vec<Vec<usize>> = (0..5)
+            .map(|seed| play(minimax(Difficulty::Easy, seed)))
+            .collect();
+        assert!(games.iter().any(|game| *game != games[0]));
+    }
 }
@@ -0,0 +1,56 @@
+use std::process;
+use std::time::Duration;
+use tictactoe::cli;
+use tictactoe::network::reconnect::ReconnectPolicy;
+use tictactoe::server;
+
+const USAGE: &str = "\
+Usage: tictactoe-server [--port PORT] [--grace-period SECS] [--stand-in STRATEGY]
+
+Hosts tic tac toe games for players who connect with `tictactoe --server <address>`.
+The port defaults to 7878.
+
+Players whose connection drops have --grace-period seconds to come back, 60 by default.
+After that they forfeit the game, or the --stand-in AI plays for them, e.g. 'minimax:hard'.";
+
+const DEFAULT_PORT: u16 = 7878;
+
+fn main() {
+    let mut port = DEFAULT_PORT;
+    let mut policy = ReconnectPolicy::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{USAGE}");
+            return;
+        }
+        let Some(value) = args.next() else {
+            exit_with_usage();
+        };
+        let valid = match flag.as_str() {
+            "--port" => value.parse().map(|value| port = value).is_ok(),
+            "--grace-period" => value
+                .parse()
+                .map(|seconds| policy.grace_period = Duration::from_secs(seconds))
+                .is_ok(),
+            "--stand-in" => cli::parse_contender(&value)
+                .map(|contender| policy.stand_in = Some(contender.strategy))
+                .is_some(),
+            _ => exit_with_usage(),
+        };
+        if !valid {
+            eprintln!("{value} is not a valid value for {flag}.");
+            process::exit(1);
+        }
+    }
+
+    if let Err(e) = server::run(port, policy) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(1);
+}
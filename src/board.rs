@@ -1,13 +1,22 @@
-use crate::{player::base_player::PlayerPiece, GameState};
-use std::{f32, ops::Deref};
+use crate::{player::base_player::PlayerPiece, rng::Rng};
+use std::ops::Deref;
 use synonym::Synonym;
 
 #[derive(Clone, Debug)]
 pub struct Board {
     data: Vec<CellState>,
     width: usize,
+    height: usize,
+    /// The Zobrist hash of the board as seen through each of the symmetries in `Board::symmetric_index`.
+    /// These are kept up to date whenever a cell changes, so that hashing a board is practically free.
+    hashes: [u64; SYMMETRY_COUNT],
 }
 
+/// A square board looks the same after being rotated or mirrored in 8 different ways (counting doing nothing at all)
+const SYMMETRY_COUNT: usize = 8;
+/// A rectangular board can only be mirrored or turned upside down, since turning it sideways changes its shape
+const RECTANGULAR_SYMMETRY_COUNT: usize = 4;
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum CellState {
     Empty,
@@ -57,6 +66,7 @@ pub enum Diagonal {
 pub enum BoardError {
     CellOccupied(usize),
     OutOfBounds(usize),
+    InvalidDimensions(usize, usize),
 }
 
 impl std::fmt::Display for BoardError {
@@ -64,36 +74,65 @@ impl std::fmt::Display for BoardError {
         match self {
             BoardError::CellOccupied(i) => write!(f, "{i} is not a legal move"),
             BoardError::OutOfBounds(i) => write!(f, "Move {i} would be out of bounds"),
+            BoardError::InvalidDimensions(cells, width) => {
+                write!(f, "{cells} cells can't be laid out as a board {width} cells wide")
+            }
         }
     }
 }
 
-const BOARD_STANDARD_WIDTH: usize = 3;
+/// A random-looking but fixed number for each combination of cell and piece, used for Zobrist hashing.
+///
+/// Rather than storing a table of random numbers, we use the cell index and piece as the seed for a random number generator, and take its first number.
+fn zobrist_key(index: usize, piece: PlayerPiece) -> u64 {
+    Rng::new((index as u64) << 8 | *piece as u64).next_u64()
+}
+
+pub const BOARD_STANDARD_WIDTH: usize = 3;
+pub const BOARD_MIN_WIDTH: usize = 3;
+pub const BOARD_MAX_WIDTH: usize = 15;
 
 impl Board {
-    pub fn new() -> Board {
-        Board {
-            data: vec![CellState::Empty; BOARD_STANDARD_WIDTH * BOARD_STANDARD_WIDTH],
-            width: BOARD_STANDARD_WIDTH,
-        }
+    /// Creates an empty board that is `width` cells wide and `height` cells tall.
+    pub fn new(width: usize, height: usize) -> Result<Board, BoardError> {
+        Board::new_from(vec![CellState::Empty; width * height], width)
     }
 
-    /// Creates a new board with given vector as board data. Panics if the resulting board is uneven.
-    pub fn new_from(data: Vec<CellState>) -> Board {
-        let computed_width = f32::sqrt(data.len() as f32);
-        if computed_width % 1.0 != 0.0 {
-            panic!("Attempted to make a board of uneven size!");
+    /// Creates a new board with given vector as board data, laid out in rows of `width` cells.
+    ///
+    /// Fails if the data is empty, or can't be split into rows of equal length.
+    pub fn new_from(data: Vec<CellState>, width: usize) -> Result<Board, BoardError> {
+        if width == 0 || data.is_empty() || !data.len().is_multiple_of(width) {
+            return Err(BoardError::InvalidDimensions(data.len(), width));
         }
-        Board {
-            data,
-            width: computed_width.floor() as usize,
+        let mut board = Board {
+            height: data.len() / width,
+            data: vec![CellState::Empty; data.len()],
+            width,
+            hashes: [0; SYMMETRY_COUNT],
+        };
+        for (index, cell) in data.into_iter().enumerate() {
+            board.modify_at_cell(index, cell);
         }
+        Ok(board)
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     /// Checks whther a move to a given index  on the board is valid.
     pub fn is_valid_move(&self, index: usize) -> Result<(), BoardError> {
         if index > self.len() - 1 {
@@ -107,51 +146,23 @@ impl Board {
     /// Directly edits the game board at specified index, and adds the piece.
     // TODO: Do some error checking here, or redo the return value
     pub fn place(&mut self, index: usize, piece_to_place: PlayerPiece) -> Result<(), BoardError> {
-        self.data[index] = CellState::Player(piece_to_place);
+        self.modify_at_cell(index, CellState::Player(piece_to_place));
         Ok(())
     }
 
-    // TODO: Move this responsibility to a separate module/object
-    pub fn render(&self, game: &GameState) {
-        println!();
-        println!("The board currently looks like this:");
-        for row in &self.get_all_rows() {
-            for (i, cell) in row.iter().enumerate() {
-                match cell {
-                    CellState::Empty => print!(" "),
-                    CellState::Player(piece) => {
-                        for p in game.players().iter() {
-                            if *piece == p.player_piece {
-                                print!("{}", p.name);
-                            }
-                        }
-                    }
-                }
-                if i < self.width - 1 {
-                    print!(" | ");
-                }
-            }
-            println!();
-            println!("__|___|___");
+    /// Gets the state of the cell at the given row and column, or None if that is outside the board
+    pub fn get_cell(&self, row: isize, col: isize) -> Option<CellState> {
+        if (0..self.height as isize).contains(&row) && (0..self.width as isize).contains(&col) {
+            Some(self.data[row as usize * self.width + col as usize])
+        } else {
+            None
         }
-        println!();
     }
 
-    // TODO: This should not be part of the board object, but instead part of `GameState` or somesuch
-    pub fn render_help(&self) {
-        println!();
-        println!("This is how you designate the board cells:");
-        for (row_index, row) in self.get_all_rows().iter().enumerate() {
-            for (col_index, _) in row.iter().enumerate() {
-                print!("{}", (row_index * self.width + col_index));
-                if col_index < self.width - 1 {
-                    print!(" | ");
-                }
-            }
-            println!();
-            println!("__|___|___");
-        }
-        println!();
+    /// Removes every piece from the board, keeping its dimensions
+    pub fn clear(&mut self) {
+        self.data.fill(CellState::Empty);
+        self.hashes = [0; SYMMETRY_COUNT];
     }
 
     /// Checks whether the board is full of `Player` pieces
@@ -163,9 +174,55 @@ impl Board {
     /// A very sketchy method that directly mutates the value at cell index.
     /// Use cautiously!
     pub fn modify_at_cell(&mut self, pos: usize, new_value: CellState) {
+        // XOR-ing a key in a second time takes it out again, so we remove the old piece and add the new one
+        for cell in [self.data[pos], new_value] {
+            if let CellState::Player(piece) = cell {
+                for symmetry in 0..SYMMETRY_COUNT {
+                    let index = self.symmetric_index(pos, symmetry);
+                    self.hashes[symmetry] ^= zobrist_key(index, piece);
+                }
+            }
+        }
         self.data[pos] = new_value;
     }
 
+    /// A Zobrist hash of the board: a number that is (almost certainly) different for any two different boards,
+    /// except that it is the same for all boards that are rotations or mirror images of each other.
+    ///
+    /// Such boards are equally good for the same player, so the AI only has to figure out one of them.
+    pub fn canonical_hash(&self) -> u64 {
+        let symmetry_count = if self.width == self.height {
+            SYMMETRY_COUNT
+        } else {
+            RECTANGULAR_SYMMETRY_COUNT
+        };
+        self.hashes[..symmetry_count]
+            .iter()
+            .copied()
+            .min()
+            .expect("There is always at least one symmetry")
+    }
+
+    /// Finds where the cell at `index` ends up after rotating or mirroring the board.
+    ///
+    /// Symmetries 0-3 work on any board: doing nothing, mirroring left-right, mirroring top-bottom and turning it upside down.
+    /// Symmetries 4-7 turn the board sideways, so they only make sense for square boards. On other boards they are only used to keep the hashes ticking.
+    fn symmetric_index(&self, index: usize, symmetry: usize) -> usize {
+        let (row, col) = (index / self.width, index % self.width);
+        let (last_row, last_col) = (self.height - 1, self.width - 1);
+        let (new_row, new_col) = match symmetry {
+            0 => (row, col),
+            1 => (row, last_col - col),
+            2 => (last_row - row, col),
+            3 => (last_row - row, last_col - col),
+            4 => (col, row),
+            5 => (col, last_row - row),
+            6 => (last_col - col, row),
+            _ => (last_col - col, last_row - row),
+        };
+        new_row * self.width + new_col
+    }
+
     /// Returns a vector of indices on the board that are empty
     pub fn get_indices_of_empty_cells(&self) -> Vec<usize> {
         self.data
@@ -181,6 +238,34 @@ impl Board {
             .collect()
     }
 
+    /// Returns the indices of empty cells that are next to a piece, including diagonally.
+    ///
+    /// On an empty board there are no such cells, so we return the centre cell instead, which is as good a start as any.
+    pub fn get_indices_of_empty_cells_near_pieces(&self) -> Vec<usize> {
+        let is_next_to_a_piece = |index: usize| {
+            let (row, col) = ((index / self.width) as isize, (index % self.width) as isize);
+            (-1..=1).any(|row_step| {
+                (-1..=1).any(|col_step| {
+                    matches!(
+                        self.get_cell(row + row_step, col + col_step),
+                        Some(CellState::Player(_))
+                    )
+                })
+            })
+        };
+        let neighbouring_cells: Vec<usize> = self
+            .get_indices_of_empty_cells()
+            .into_iter()
+            .filter(|&index| is_next_to_a_piece(index))
+            .collect();
+
+        if neighbouring_cells.is_empty() && !self.is_full() {
+            vec![(self.height / 2) * self.width + self.width / 2]
+        } else {
+            neighbouring_cells
+        }
+    }
+
     /// Get a copy of a row
     ///
     /// * `row_num`: the row number, counted from 0 and from the top
@@ -195,7 +280,7 @@ impl Board {
 
     /// Get all rows, from top to bottom
     pub fn get_all_rows(&self) -> Vec<BoardRow> {
-        (0..self.width).map(|n| self.get_row(n)).collect()
+        (0..self.height).map(|n| self.get_row(n)).collect()
     }
 
     /// Get a copy of a column
@@ -219,25 +304,280 @@ impl Board {
         (0..self.width).map(|n| self.get_column(n)).collect()
     }
 
-    /// Gets a copy of given diagonal from the Board.
+    /// Gets copies of every diagonal running in the given direction, including the short ones in the corners.
     ///
     /// A diagonal is either a Major diagonal, or a Minor diagonal (also called antidiagonal).
     /// A major diagonal is from top left to bottom right; a Minor is from top right to bottom left
-    pub fn get_diagonal(&self, diagonal: Diagonal) -> BoardDiagonal {
-        let diagonal_num: usize = match diagonal {
+    pub fn get_all_diagonals(&self, diagonal: Diagonal) -> Vec<BoardDiagonal> {
+        // Every diagonal starts on the top row, or on the side of the board it points away from
+        let side_column = match diagonal {
             Diagonal::Major => 0,
-            Diagonal::Minor => 1,
+            Diagonal::Minor => self.width - 1,
         };
-        let board_size_coefficient = self.width - 1;
-        let step = (self.width + 1) / std::cmp::max(1, board_size_coefficient * diagonal_num);
-        BoardDiagonal(
-            self.data
-                .iter()
-                .skip(diagonal_num * board_size_coefficient)
-                .step_by(step)
-                .take(self.width)
-                .copied()
-                .collect(),
-        )
+        let top_row_starts = (0..self.width).map(|col| (0, col));
+        let side_starts = (1..self.height).map(|row| (row, side_column));
+
+        top_row_starts
+            .chain(side_starts)
+            .map(|(row, col)| self.get_diagonal_from(row, col, diagonal))
+            .collect()
+    }
+
+    /// Gets a copy of the diagonal that starts at the given cell and runs until it hits an edge.
+    fn get_diagonal_from(&self, row: usize, col: usize, diagonal: Diagonal) -> BoardDiagonal {
+        let mut cells = vec![];
+        let (mut row, mut col) = (row, col as isize);
+        let col_step = match diagonal {
+            Diagonal::Major => 1,
+            Diagonal::Minor => -1,
+        };
+        while row < self.height && (0..self.width as isize).contains(&col) {
+            cells.push(self.data[row * self.width + col as usize]);
+            row += 1;
+            col += col_step;
+        }
+        BoardDiagonal(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: std::ops::RangeInclusive<usize> = 3..=7;
+
+    /// Boards that are wider than they are tall and taller than they are wide
+    const NON_SQUARE: [(usize, usize); 4] = [(7, 6), (4, 5), (6, 3), (3, 7)];
+
+    /// A board where every cell holds a piece numbered after its index, so lines can be told apart by what is in them
+    fn numbered_board(width: usize, height: usize) -> Board {
+        let data = (0..width * height)
+            .map(|index| CellState::Player(PlayerPiece::new(index as u8)))
+            .collect();
+        Board::new_from(data, width).unwrap()
+    }
+
+    fn indices(cells: &[CellState]) -> Vec<usize> {
+        cells
+            .iter()
+            .map(|cell| match cell {
+                CellState::Player(piece) => **piece as usize,
+                CellState::Empty => panic!("The numbered board has no empty cells"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn new_boards_are_empty() {
+        for size in SIZES {
+            let board = Board::new(size, size).unwrap();
+            assert_eq!((board.width(), board.height()), (size, size));
+            assert_eq!(board.len(), size * size);
+            assert_eq!(board.get_indices_of_empty_cells().len(), size * size);
+            assert!(!board.is_full());
+            for index in 0..size * size {
+                assert!(board.is_valid_move(index).is_ok());
+            }
+            assert!(matches!(
+                board.is_valid_move(size * size),
+                Err(BoardError::OutOfBounds(_))
+            ));
+        }
+        assert!(Board::new(0, 3).is_err());
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        for size in SIZES {
+            let board = numbered_board(size, size);
+            let rows = board.get_all_rows();
+            let columns = board.get_all_columns();
+            assert_eq!((rows.len(), columns.len()), (size, size));
+            for n in 0..size {
+                let row: Vec<usize> = (0..size).map(|col| n * size + col).collect();
+                let column: Vec<usize> = (0..size).map(|row| row * size + n).collect();
+                assert_eq!(indices(&rows[n]), row, "row {n} of {size}x{size}");
+                assert_eq!(indices(&columns[n]), column, "column {n} of {size}x{size}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_diagonal_including_the_corners() {
+        for size in SIZES {
+            let board = numbered_board(size, size);
+            for direction in [Diagonal::Major, Diagonal::Minor] {
+                let diagonals = board.get_all_diagonals(direction);
+                assert_eq!(diagonals.len(), 2 * size - 1);
+
+                // Every cell is on exactly one diagonal in each direction
+                let mut seen: Vec<usize> = diagonals.iter().flat_map(|d| indices(d)).collect();
+                seen.sort();
+                assert_eq!(seen, (0..size * size).collect::<Vec<usize>>());
+
+                let mut lengths: Vec<usize> = diagonals.iter().map(|d| d.len()).collect();
+                lengths.sort();
+                let mut expected: Vec<usize> = (1..=size).chain(1..size).collect();
+                expected.sort();
+                assert_eq!(
+                    lengths, expected,
+                    "{direction:?} diagonals of {size}x{size}"
+                );
+
+                for diagonal in &diagonals {
+                    let cells = indices(diagonal);
+                    for pair in cells.windows(2) {
+                        let step = match direction {
+                            Diagonal::Major => size + 1,
+                            Diagonal::Minor => size - 1,
+                        };
+                        assert_eq!(pair[1] - pair[0], step, "{direction:?} {cells:?}");
+                    }
+                }
+            }
+            // The one-cell diagonals in the corners
+            let major = board.get_all_diagonals(Diagonal::Major);
+            let minor = board.get_all_diagonals(Diagonal::Minor);
+            assert!(major.iter().any(|d| indices(d) == [size - 1]));
+            assert!(major.iter().any(|d| indices(d) == [size * (size - 1)]));
+            assert!(minor.iter().any(|d| indices(d) == [0]));
+            assert!(minor.iter().any(|d| indices(d) == [size * size - 1]));
+        }
+    }
+
+    #[test]
+    fn rows_and_columns_of_non_square_boards() {
+        for (width, height) in NON_SQUARE {
+            let board = numbered_board(width, height);
+            assert_eq!(board.get_all_rows().len(), height);
+            assert_eq!(board.get_all_columns().len(), width);
+            for n in 0..height {
+                let row: Vec<usize> = (0..width).map(|col| n * width + col).collect();
+                assert_eq!(
+                    indices(&board.get_row(n)),
+                    row,
+                    "row {n} of {width}x{height}"
+                );
+            }
+            for n in 0..width {
+                let column: Vec<usize> = (0..height).map(|row| row * width + n).collect();
+                assert_eq!(
+                    indices(&board.get_column(n)),
+                    column,
+                    "column {n} of {width}x{height}"
+                );
+            }
+            // Asking past the edge gives nothing rather than cells from somewhere else
+            assert!(board.get_row(height).is_empty());
+            assert!(board.get_column(width).is_empty());
+        }
+    }
+
+    #[test]
+    fn diagonals_of_non_square_boards() {
+        for (width, height) in NON_SQUARE {
+            let board = numbered_board(width, height);
+            for direction in [Diagonal::Major, Diagonal::Minor] {
+                let diagonals = board.get_all_diagonals(direction);
+                assert_eq!(diagonals.len(), width + height - 1);
+
+                let mut seen: Vec<usize> = diagonals.iter().flat_map(|d| indices(d)).collect();
+                seen.sort();
+                assert_eq!(seen, (0..width * height).collect::<Vec<usize>>());
+
+                // The longest diagonals go all the way across the short side, and there are as many of them as the
+                // long side has room for
+                let longest = width.min(height);
+                let lengths: Vec<usize> = diagonals.iter().map(|d| d.len()).collect();
+                assert_eq!(lengths.iter().max(), Some(&longest));
+                assert_eq!(
+                    lengths.iter().filter(|&&len| len == longest).count(),
+                    width.abs_diff(height) + 1,
+                    "{direction:?} diagonals of {width}x{height}"
+                );
+
+                let step = match direction {
+                    Diagonal::Major => width + 1,
+                    Diagonal::Minor => width - 1,
+                };
+                for diagonal in &diagonals {
+                    let cells = indices(diagonal);
+                    for pair in cells.windows(2) {
+                        assert_eq!(pair[1] - pair[0], step, "{direction:?} {cells:?}");
+                    }
+                }
+            }
+        }
+
+        // On a wide board, diagonals starting along the top run off the bottom before reaching the far side
+        let wide = numbered_board(7, 6);
+        let major = wide.get_all_diagonals(Diagonal::Major);
+        assert!(major.iter().any(|d| indices(d) == [1, 9, 17, 25, 33, 41]));
+        assert!(major.iter().any(|d| indices(d) == [3, 11, 19, 27]));
+        let minor = wide.get_all_diagonals(Diagonal::Minor);
+        assert!(minor.iter().any(|d| indices(d) == [5, 11, 17, 23, 29, 35]));
+        assert!(minor.iter().any(|d| indices(d) == [13, 19, 25, 31, 37]));
+
+        // On a tall board, diagonals starting down the side run off the far side before reaching the bottom
+        let tall = numbered_board(4, 5);
+        let major = tall.get_all_diagonals(Diagonal::Major);
+        assert!(major.iter().any(|d| indices(d) == [4, 9, 14, 19]));
+        assert!(major.iter().any(|d| indices(d) == [8, 13, 18]));
+        let minor = tall.get_all_diagonals(Diagonal::Minor);
+        assert!(minor.iter().any(|d| indices(d) == [7, 10, 13, 16]));
+        assert!(minor.iter().any(|d| indices(d) == [11, 14, 17]));
+    }
+
+    #[test]
+    fn wins_on_non_square_boards() {
+        use crate::gamestate::GameReferee;
+
+        // (width, height, win length, cells of a winning line)
+        let wins: [(usize, usize, usize, &[usize]); 6] = [
+            // Along the bottom row of a wide board
+            (7, 6, 4, &[38, 39, 40, 41]),
+            // Down the last column
+            (7, 6, 4, &[13, 20, 27, 34]),
+            // A major diagonal ending in the bottom corner
+            (7, 6, 4, &[17, 25, 33, 41]),
+            // A minor diagonal starting on the right edge below the top
+            (7, 6, 4, &[13, 19, 25, 31]),
+            // The whole height of a tall board
+            (4, 5, 5, &[2, 6, 10, 14, 18]),
+            // A diagonal across the whole width of a tall board
+            (4, 5, 4, &[7, 10, 13, 16]),
+        ];
+        for (width, height, win_length, cells) in wins {
+            let referee = GameReferee::new(win_length);
+            let mut board = Board::new(width, height).unwrap();
+            let piece = PlayerPiece::new(1);
+            for &index in &cells[..cells.len() - 1] {
+                board.place(index, piece).unwrap();
+            }
+            assert_eq!(
+                referee.adjudicate(&board),
+                None,
+                "{width}x{height} {cells:?}"
+            );
+            let last = cells[cells.len() - 1];
+            board.place(last, piece).unwrap();
+            assert_eq!(
+                referee.adjudicate(&board),
+                Some(piece),
+                "{width}x{height} {cells:?}"
+            );
+            for &index in cells {
+                assert_eq!(referee.adjudicate_move(&board, index), Some(piece));
+            }
+        }
+
+        // A tall board is too narrow for a row of five, even when a whole row is filled
+        let referee = GameReferee::new(5);
+        let mut board = Board::new(4, 5).unwrap();
+        for index in 16..20 {
+            board.place(index, PlayerPiece::new(1)).unwrap();
+        }
+        assert_eq!(referee.adjudicate(&board), None);
+        assert_eq!(referee.adjudicate_move(&board, 19), None);
     }
 }
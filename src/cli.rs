@@ -0,0 +1,768 @@
+use crate::ai::{AIStrategy, Difficulty};
+use crate::board::{BOARD_MAX_WIDTH, BOARD_MIN_WIDTH, Board};
+use crate::config::{self, Config};
+use crate::gamestate::MIN_WIN_LENGTH;
+use crate::network::reconnect::ReconnectPolicy;
+use crate::render::Style;
+use crate::selfplay::Contender;
+use std::cmp;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: tictactoe [OPTIONS]
+
+Without options, the game asks you how you want to play.
+
+Options:
+  --width N            Width of the board, from 3 to 15
+  --height N           Height of the board, from 3 to 15. Defaults to the width
+  --win-length N       How many pieces in a row it takes to win
+  --load FILE          Go on with a game saved with 'save <file>' or --save
+  --save FILE          Save the game to FILE after every turn, so it can be picked up again with --load
+  --style STYLE        How to draw the board: ascii, unicode or color. Defaults to ascii
+  --tui                Play in a full-screen terminal UI, moving a cursor with the arrow keys or hjkl
+  --player1 PLAYER     Who player 1 is: human, remote or a strategy. Defaults to human,
+                       or to minimax in self-play mode
+  --player2 PLAYER     Who player 2 is: human, remote or a strategy. Defaults to minimax,
+                       or to remote with --host
+  --name1 NAME         Player 1's name. Defaults to their symbol
+  --name2 NAME         Player 2's name. Defaults to their symbol
+  --symbol1 CHAR       The symbol player 1's pieces are drawn with. Defaults to x
+  --symbol2 CHAR       The symbol player 2's pieces are drawn with. Defaults to y
+  --first N            Which player goes first, 1 or 2. Defaults to 1
+  --difficulty LEVEL   How good the AIs are, for strategies that don't say: easy, medium, hard or perfect
+  --seed N             Seed for the AIs' random choices, to make games repeatable
+  --think-time MS      How long a minimax AI may think about each move, in milliseconds. Defaults to 500
+  --think-time1 MS     How long player 1 may think, if it is a minimax AI. Defaults to --think-time
+  --think-time2 MS     How long player 2 may think, if it is a minimax AI. Defaults to --think-time
+  --self-play GAMES    Let two AIs play GAMES games against each other, and print statistics
+  --host PORT          Host a game for someone on another computer. The usual port is 7878
+  --join ADDRESS       Join a game someone else is hosting, e.g. localhost:7878
+  --server ADDRESS     Play on a game server, e.g. localhost:7878. The board options are used for games you create
+  --web PORT           Play in your web browser, at http://localhost:PORT/
+  --games-dir DIR      Where --web keeps correspondence games. Defaults to tictactoe-games
+  --grace-period SECS  How long the other player has to come back if the connection drops. Defaults to 60
+  --stand-in STRATEGY  An AI that takes over from the other player if they don't come back in time.
+                       Without one, they forfeit the game
+  --config FILE        Read defaults from FILE, instead of ~/.config/tictactoe/config.toml.
+                       Anything on the command line wins over the file
+  --help               Print this message
+
+Strategies are 'minimax' or 'mcts', optionally followed by a difficulty,
+like 'mcts:easy'. The difficulties are easy, medium, hard and perfect.
+
+Any of --player1, --player2, --name1, --name2, --symbol1, --symbol2 and --first
+sets the players up without asking. A remote player joins the game you --host,
+as player 2.";
+
+/// Everything that can be set from the command line. Anything left out is asked for, or given a default.
+#[derive(Default)]
+pub struct CliOptions {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub win_length: Option<usize>,
+    pub load_path: Option<PathBuf>,
+    pub save_path: Option<PathBuf>,
+    pub style: Option<Style>,
+    /// Whether the players at this computer use the full-screen terminal UI
+    pub tui: bool,
+    pub self_play_games: Option<u32>,
+    pub player_1: Option<PlayerChoice>,
+    pub player_2: Option<PlayerChoice>,
+    pub name_1: Option<String>,
+    pub name_2: Option<String>,
+    pub symbol_1: Option<char>,
+    pub symbol_2: Option<char>,
+    /// Which player goes first, 1 or 2
+    pub first: Option<u8>,
+    pub difficulty: Option<Difficulty>,
+    pub seed: Option<u64>,
+    /// How long a minimax AI may think about each move
+    pub think_time: Option<Duration>,
+    /// How long each seat's minimax AI may think about each move, in seat order. These win over `think_time`.
+    pub seat_think_times: [Option<Duration>; 2],
+    pub host_port: Option<u16>,
+    pub join_address: Option<String>,
+    pub server_address: Option<String>,
+    pub web_port: Option<u16>,
+    pub games_dir: Option<PathBuf>,
+    pub grace_period: Option<u64>,
+    pub stand_in: Option<Contender>,
+    pub config_path: Option<PathBuf>,
+    /// The defaults from the config file, which the options above win over
+    pub config: Config,
+    pub help_wanted: bool,
+}
+
+/// Who plays a seat, as given with --player1 or --player2
+pub enum PlayerChoice {
+    Human,
+    /// Someone on another computer, who joins the game we host
+    Remote,
+    AI(Contender),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+    NeedsNetwork(String),
+    NeedsWeb(String),
+    Conflict(String, String),
+    InvalidWinLength(usize, usize),
+    /// The config file could not be read, or has a setting we can't use
+    Config(String),
+    /// Both players were given the same symbol
+    SameSymbols(char),
+    /// Self-play is only for AIs, but the given player is not one
+    NeedsAI(String),
+    /// A remote player was asked for without hosting a game for them to join
+    RemoteNeedsHost(String),
+    /// When hosting, we are player 1 and go first, and the remote player is player 2
+    HostPlaysFirst(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownFlag(flag) => write!(f, "Unknown option {flag}. Try --help."),
+            CliError::MissingValue(flag) => write!(f, "{flag} needs a value."),
+            CliError::InvalidValue(flag, value) => {
+                write!(f, "{value} is not a valid value for {flag}.")
+            }
+            CliError::NeedsNetwork(flag) => {
+                write!(
+                    f,
+                    "{flag} only works together with --host, --join or --server."
+                )
+            }
+            CliError::NeedsWeb(flag) => write!(f, "{flag} only works together with --web."),
+            CliError::Conflict(flag, other_flag) => {
+                write!(f, "{flag} and {other_flag} can't be used together.")
+            }
+            CliError::InvalidWinLength(win_length, longest_line) => write!(
+                f,
+                "A win length of {win_length} does not work on this board. Pick a number from {MIN_WIN_LENGTH} to {longest_line}."
+            ),
+            CliError::Config(message) => write!(f, "{message}"),
+            CliError::SameSymbols(symbol) => {
+                write!(f, "Both players can't play with {symbol}.")
+            }
+            CliError::NeedsAI(flag) => write!(f, "{flag} has to be an AI in self-play mode."),
+            CliError::RemoteNeedsHost(flag) => write!(
+                f,
+                "{flag} is remote, which only works together with --host."
+            ),
+            CliError::HostPlaysFirst(flag) => write!(
+                f,
+                "{flag} does not work with --host. The host is a human and goes first as player 1, and the remote player is player 2."
+            ),
+        }
+    }
+}
+
+/// Parses the command line arguments, not including the program name
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliOptions, CliError> {
+    let mut options = CliOptions::default();
+    // Players are only made sense of at the end, since --difficulty, --seed and --think-time may come after them
+    let mut player_values: [Option<String>; 2] = [None, None];
+    let mut stand_in_value = None;
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            options.help_wanted = true;
+            continue;
+        }
+        if flag == "--tui" {
+            options.tui = true;
+            continue;
+        }
+        let value = match flag.as_str() {
+            "--width" | "--height" | "--win-length" | "--load" | "--save" | "--style"
+            | "--self-play" | "--player1" | "--player2" | "--name1" | "--name2" | "--symbol1"
+            | "--symbol2" | "--first" | "--difficulty" | "--seed" | "--think-time"
+            | "--think-time1" | "--think-time2" | "--host" | "--join" | "--server" | "--web"
+            | "--games-dir" | "--grace-period" | "--stand-in" | "--config" => args
+                .next()
+                .ok_or_else(|| CliError::MissingValue(flag.clone()))?,
+            _ => return Err(CliError::UnknownFlag(flag)),
+        };
+        let invalid = || CliError::InvalidValue(flag.clone(), value.clone());
+        match flag.as_str() {
+            "--width" => options.width = Some(parse_board_side(&value).ok_or_else(invalid)?),
+            "--height" => options.height = Some(parse_board_side(&value).ok_or_else(invalid)?),
+            "--win-length" => {
+                options.win_length = Some(value.parse().map_err(|_| invalid())?);
+            }
+            "--load" => options.load_path = Some(PathBuf::from(&value)),
+            "--save" => options.save_path = Some(PathBuf::from(&value)),
+            "--style" => options.style = Some(parse_style(&value).ok_or_else(invalid)?),
+            "--self-play" => {
+                options.self_play_games = Some(value.parse().map_err(|_| invalid())?);
+            }
+            "--player1" => player_values[0] = Some(value.clone()),
+            "--player2" => player_values[1] = Some(value.clone()),
+            "--name1" => options.name_1 = Some(value.clone()),
+            "--name2" => options.name_2 = Some(value.clone()),
+            "--symbol1" => options.symbol_1 = Some(parse_symbol(&value).ok_or_else(invalid)?),
+            "--symbol2" => options.symbol_2 = Some(parse_symbol(&value).ok_or_else(invalid)?),
+            "--first" => {
+                options.first = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|first| [1, 2].contains(first))
+                        .ok_or_else(invalid)?,
+                );
+            }
+            "--difficulty" => {
+                options.difficulty = Some(parse_difficulty(&value).ok_or_else(invalid)?);
+            }
+            "--seed" => options.seed = Some(value.parse().map_err(|_| invalid())?),
+            "--think-time" => {
+                options.think_time = Some(parse_think_time(&value).ok_or_else(invalid)?);
+            }
+            "--think-time1" => {
+                options.seat_think_times[0] = Some(parse_think_time(&value).ok_or_else(invalid)?);
+            }
+            "--think-time2" => {
+                options.seat_think_times[1] = Some(parse_think_time(&value).ok_or_else(invalid)?);
+            }
+            "--host" => options.host_port = Some(value.parse().map_err(|_| invalid())?),
+            "--join" => options.join_address = Some(value.clone()),
+            "--server" => options.server_address = Some(value.clone()),
+            "--web" => options.web_port = Some(value.parse().map_err(|_| invalid())?),
+            "--games-dir" => options.games_dir = Some(PathBuf::from(&value)),
+            "--grace-period" => options.grace_period = Some(value.parse().map_err(|_| invalid())?),
+            "--config" => options.config_path = Some(PathBuf::from(&value)),
+            _ => stand_in_value = Some(value.clone()),
+        }
+    }
+    // Asking for help should work even when the config file is broken
+    if !options.help_wanted {
+        options.config = config::load(options.config_path.as_deref())
+            .map_err(|e| CliError::Config(e.to_string()))?;
+    }
+    let difficulty = options.ai_difficulty();
+    if let Some(value) = stand_in_value {
+        options.stand_in = Some(
+            contender(&value, difficulty, options.seed, options.ai_think_time())
+                .ok_or_else(|| CliError::InvalidValue("--stand-in".to_string(), value))?,
+        );
+    }
+    let think_times = [0, 1].map(|seat| options.seat_think_time(seat));
+    let [player_1, player_2] = player_values;
+    for (flag, value, think_time, choice) in [
+        ("--player1", player_1, think_times[0], &mut options.player_1),
+        ("--player2", player_2, think_times[1], &mut options.player_2),
+    ] {
+        if let Some(value) = value {
+            *choice = Some(
+                parse_player(&value, difficulty, options.seed, think_time)
+                    .ok_or_else(|| CliError::InvalidValue(flag.to_string(), value))?,
+            );
+        }
+    }
+
+    check_combinations(&options)?;
+    Ok(options)
+}
+
+/// Makes sure the options make sense together, e.g. that only one way of playing was picked
+fn check_combinations(options: &CliOptions) -> Result<(), CliError> {
+    let networked = options.host_port.is_some()
+        || options.join_address.is_some()
+        || options.server_address.is_some();
+    if !networked {
+        if options.grace_period.is_some() {
+            return Err(CliError::NeedsNetwork("--grace-period".to_string()));
+        }
+        if options.stand_in.is_some() {
+            return Err(CliError::NeedsNetwork("--stand-in".to_string()));
+        }
+    }
+    if options.web_port.is_none() && options.games_dir.is_some() {
+        return Err(CliError::NeedsWeb("--games-dir".to_string()));
+    }
+    // Only one way of playing at a time
+    let modes = [
+        ("--self-play", options.self_play_games.is_some()),
+        ("--host", options.host_port.is_some()),
+        ("--join", options.join_address.is_some()),
+        ("--server", options.server_address.is_some()),
+        ("--web", options.web_port.is_some()),
+        ("--load", options.load_path.is_some()),
+    ];
+    let mut chosen_modes = modes.iter().filter(|(_, chosen)| *chosen);
+    if let (Some((flag, _)), Some((other_flag, _))) = (chosen_modes.next(), chosen_modes.next()) {
+        return Err(CliError::Conflict(flag.to_string(), other_flag.to_string()));
+    }
+    let mode = chosen_mode(&modes);
+    // Only games played at this computer can be saved, and self-play games are over before anyone could load them
+    if options.save_path.is_some()
+        && let Some(mode) = mode
+        && mode != "--load"
+    {
+        return Err(CliError::Conflict("--save".to_string(), mode.to_string()));
+    }
+    // Nobody sits at this computer to play in self-play games, and in a browser the page is the UI
+    if options.tui
+        && let Some(mode @ ("--self-play" | "--web")) = mode
+    {
+        return Err(CliError::Conflict("--tui".to_string(), mode.to_string()));
+    }
+    // The host picks the board, so whoever joins has no say in it. In a browser, the board is picked on the page,
+    // and a saved game comes with its own board.
+    if let Some(mode @ ("--join" | "--web" | "--load")) = mode {
+        for (flag, value) in [
+            ("--width", options.width),
+            ("--height", options.height),
+            ("--win-length", options.win_length),
+        ] {
+            if value.is_some() {
+                return Err(CliError::Conflict(flag.to_string(), mode.to_string()));
+            }
+        }
+    }
+    check_players(options, mode)
+}
+
+/// Makes sure the players can be set up the way the options say, in the way of playing that was picked
+fn check_players(options: &CliOptions, mode: Option<&str>) -> Result<(), CliError> {
+    let players = [
+        ("--player1", options.player_1.as_ref()),
+        ("--player2", options.player_2.as_ref()),
+    ];
+    let player_flags = [
+        ("--player1", options.player_1.is_some()),
+        ("--player2", options.player_2.is_some()),
+        ("--name1", options.name_1.is_some()),
+        ("--name2", options.name_2.is_some()),
+        ("--symbol1", options.symbol_1.is_some()),
+        ("--symbol2", options.symbol_2.is_some()),
+        ("--first", options.first.is_some()),
+    ];
+    let given = |flags: &[&str]| {
+        player_flags
+            .iter()
+            .find(|(flag, given)| *given && flags.contains(flag))
+            .map(|(flag, _)| flag.to_string())
+    };
+    if let (Some(symbol_1), Some(symbol_2)) = (options.symbol_1, options.symbol_2)
+        && symbol_1 == symbol_2
+    {
+        return Err(CliError::SameSymbols(symbol_1));
+    }
+    match mode {
+        // Whoever we end up playing with, and which seat we get, is only known once we're connected.
+        // A saved game comes with its own players, and in a browser they are picked on the page.
+        Some(mode @ ("--join" | "--server" | "--web" | "--load")) => {
+            let all = player_flags.map(|(flag, _)| flag);
+            if let Some(flag) = given(&all) {
+                return Err(CliError::Conflict(flag, mode.to_string()));
+            }
+        }
+        Some("--self-play") => {
+            if let Some(flag) = given(&["--symbol1", "--symbol2", "--first"]) {
+                return Err(CliError::Conflict(flag, "--self-play".to_string()));
+            }
+            for (flag, choice) in players {
+                if choice.is_some_and(|choice| !matches!(choice, PlayerChoice::AI(_))) {
+                    return Err(CliError::NeedsAI(flag.to_string()));
+                }
+            }
+        }
+        Some("--host") => {
+            if let Some(flag) = given(&["--name2", "--symbol2"]) {
+                return Err(CliError::Conflict(flag, "--host".to_string()));
+            }
+            if options.first == Some(2) {
+                return Err(CliError::HostPlaysFirst("--first 2".to_string()));
+            }
+            if options
+                .player_1
+                .as_ref()
+                .is_some_and(|choice| !matches!(choice, PlayerChoice::Human))
+            {
+                return Err(CliError::HostPlaysFirst("--player1".to_string()));
+            }
+            if options
+                .player_2
+                .as_ref()
+                .is_some_and(|choice| !matches!(choice, PlayerChoice::Remote))
+            {
+                return Err(CliError::HostPlaysFirst("--player2".to_string()));
+            }
+        }
+        _ => {
+            for (flag, choice) in players {
+                if matches!(choice, Some(PlayerChoice::Remote)) {
+                    return Err(CliError::RemoteNeedsHost(flag.to_string()));
+                }
+            }
+        }
+    }
+    // The AIs are picked in the browser, and a saved game keeps its AIs' settings
+    if let Some(mode @ ("--web" | "--load")) = mode {
+        for (flag, value) in [
+            ("--difficulty", options.difficulty.is_some()),
+            ("--seed", options.seed.is_some()),
+            ("--think-time", options.think_time.is_some()),
+            ("--think-time1", options.seat_think_times[0].is_some()),
+            ("--think-time2", options.seat_think_times[1].is_some()),
+        ] {
+            if value {
+                return Err(CliError::Conflict(flag.to_string(), mode.to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The way of playing that was picked, if any. There is at most one, once the conflicts have been checked.
+fn chosen_mode(modes: &[(&'static str, bool)]) -> Option<&'static str> {
+    modes
+        .iter()
+        .find(|(_, chosen)| *chosen)
+        .map(|(mode, _)| *mode)
+}
+
+impl CliOptions {
+    /// What happens when the connection to the other player drops, with the defaults for anything not given
+    pub fn reconnect_policy(&self) -> ReconnectPolicy {
+        let default = ReconnectPolicy::default();
+        ReconnectPolicy {
+            grace_period: self
+                .grace_period
+                .map_or(default.grace_period, Duration::from_secs),
+            stand_in: self.stand_in.as_ref().map(|contender| contender.strategy),
+        }
+    }
+
+    /// Whether the players were set up on the command line, so there is nothing to ask about them
+    pub fn players_given(&self) -> bool {
+        self.player_1.is_some()
+            || self.player_2.is_some()
+            || self.name_1.is_some()
+            || self.name_2.is_some()
+            || self.symbol_1.is_some()
+            || self.symbol_2.is_some()
+            || self.first.is_some()
+    }
+
+    /// The AI used for `seat`, counting from 0, when no strategy was given for it, with the AI settings from the
+    /// command line
+    pub fn default_contender(&self, seat: usize) -> Contender {
+        contender(
+            "minimax",
+            self.ai_difficulty(),
+            self.seed,
+            self.seat_think_time(seat),
+        )
+        .expect("minimax is a valid strategy")
+    }
+
+    /// How good the AIs are when their strategy doesn't say, from the command line or else the config file
+    pub fn ai_difficulty(&self) -> Option<Difficulty> {
+        self.difficulty.or(self.config.difficulty)
+    }
+
+    /// How long a minimax AI may think about each move, from the command line or else the config file
+    pub fn ai_think_time(&self) -> Option<Duration> {
+        self.think_time.or(self.config.think_time)
+    }
+
+    /// How long a minimax AI in `seat`, counting from 0, may think about each move. A time for the seat wins over
+    /// the one for all AIs, and the command line wins over the config file.
+    pub fn seat_think_time(&self, seat: usize) -> Option<Duration> {
+        self.seat_think_times[seat]
+            .or(self.think_time)
+            .or(self.config.players[seat].think_time)
+            .or(self.config.think_time)
+    }
+
+    /// How to draw the board, from the command line or else the config file
+    pub fn style(&self) -> Style {
+        self.style.or(self.config.style).unwrap_or_default()
+    }
+}
+
+/// Checks that a win length given on the command line fits on the board
+pub fn check_win_length(win_length: usize, board: &Board) -> Result<usize, CliError> {
+    let longest_line = cmp::max(board.width(), board.height());
+    if (MIN_WIN_LENGTH..=longest_line).contains(&win_length) {
+        Ok(win_length)
+    } else {
+        Err(CliError::InvalidWinLength(win_length, longest_line))
+    }
+}
+
+fn parse_board_side(value: &str) -> Option<usize> {
+    value
+        .parse()
+        .ok()
+        .filter(|side| (BOARD_MIN_WIDTH..=BOARD_MAX_WIDTH).contains(side))
+}
+
+fn parse_style(value: &str) -> Option<Style> {
+    match value {
+        "ascii" => Some(Style::Ascii),
+        "unicode" => Some(Style::Unicode),
+        "color" | "colour" => Some(Style::Ansi),
+        _ => None,
+    }
+}
+
+/// Parses strategies like 'minimax' and 'mcts:hard'. Leaving out the difficulty means perfect.
+pub fn parse_contender(value: &str) -> Option<Contender> {
+    contender(value, None, None, None)
+}
+
+/// Parses strategies like 'minimax' and 'mcts:hard'. Leaving out the difficulty means `difficulty`, or perfect if
+/// that isn't given either.
+fn contender(
+    value: &str,
+    difficulty: Option<Difficulty>,
+    seed: Option<u64>,
+    think_time: Option<Duration>,
+) -> Option<Contender> {
+    let (algorithm, difficulty) = match value.split_once(':') {
+        Some((algorithm, difficulty)) => (algorithm, parse_difficulty(difficulty)?),
+        None => (value, difficulty.unwrap_or(Difficulty::Perfect)),
+    };
+    let strategy = match algorithm {
+        "minimax" => AIStrategy::Minimax(difficulty.minimax_config(seed, think_time)),
+        "mcts" => AIStrategy::Mcts(difficulty.mcts_config(seed)),
+        _ => return None,
+    };
+    Some(Contender {
+        name: value.to_string(),
+        strategy,
+    })
+}
+
+/// Parses who plays a seat: 'human', 'remote', or an AI strategy
+fn parse_player(
+    value: &str,
+    difficulty: Option<Difficulty>,
+    seed: Option<u64>,
+    think_time: Option<Duration>,
+) -> Option<PlayerChoice> {
+    match value {
+        "human" => Some(PlayerChoice::Human),
+        "remote" => Some(PlayerChoice::Remote),
+        _ => contender(value, difficulty, seed, think_time).map(PlayerChoice::AI),
+    }
+}
+
+fn parse_difficulty(value: &str) -> Option<Difficulty> {
+    match value {
+        "easy" => Some(Difficulty::Easy),
+        "medium" => Some(Difficulty::Medium),
+        "hard" => Some(Difficulty::Hard),
+        "perfect" => Some(Difficulty::Perfect),
+        _ => None,
+    }
+}
+
+/// A think time is a whole number of milliseconds. The AI needs at least a little time to pick a move.
+fn parse_think_time(value: &str) -> Option<Duration> {
+    value
+        .parse()
+        .ok()
+        .filter(|&millis| millis > 0)
+        .map(Duration::from_millis)
+}
+
+/// A symbol is a single character, and a space would not show up on the board
+fn parse_symbol(value: &str) -> Option<char> {
+    let mut characters = value.chars();
+    match (characters.next(), characters.next()) {
+        (Some(symbol), None) if !symbol.is_whitespace() => Some(symbol),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Parses `args` with a config file holding `config`, so the user's own config file can't get in the way
+    fn parse(config: &str, args: &[&str]) -> Result<CliOptions, CliError> {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "tictactoe-cli-{}-{}.toml",
+            process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, config).unwrap();
+        let args = args.iter().map(|arg| arg.to_string());
+        let config_args = ["--config".to_string(), path.display().to_string()];
+        let options = parse_args(args.chain(config_args));
+        let _ = fs::remove_file(&path);
+        options
+    }
+
+    fn millis(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    /// How long the minimax AI playing `choice` may think
+    fn time_budget(choice: Option<&PlayerChoice>) -> Option<Duration> {
+        match choice {
+            Some(PlayerChoice::AI(Contender {
+                strategy: AIStrategy::Minimax(config),
+                ..
+            })) => config.time_budget,
+            _ => panic!("Expected a minimax AI"),
+        }
+    }
+
+    #[test]
+    fn each_seat_gets_its_own_think_time() {
+        let args = [
+            "--player1",
+            "minimax",
+            "--player2",
+            "minimax:hard",
+            "--think-time1",
+            "100",
+            "--think-time2",
+            "900",
+        ];
+        let options = parse("", &args).unwrap();
+        assert_eq!(time_budget(options.player_1.as_ref()), millis(100));
+        assert_eq!(time_budget(options.player_2.as_ref()), millis(900));
+    }
+
+    #[test]
+    fn seat_think_times_win_over_the_others() {
+        let config = "[ai]\nthink_time_ms = 200\n\n[player2]\nthink_time_ms = 900\n";
+        let options = parse(config, &["--player1", "minimax", "--player2", "minimax"]).unwrap();
+        assert_eq!(time_budget(options.player_1.as_ref()), millis(200));
+        assert_eq!(time_budget(options.player_2.as_ref()), millis(900));
+
+        let options = parse(config, &["--think-time", "300", "--think-time1", "50"]).unwrap();
+        let default_ai = |seat| Some(PlayerChoice::AI(options.default_contender(seat)));
+        assert_eq!(time_budget(default_ai(0).as_ref()), millis(50));
+        assert_eq!(time_budget(default_ai(1).as_ref()), millis(300));
+    }
+
+    #[test]
+    fn flags_that_do_not_go_together() {
+        let remote_needs_host = "--player2 is remote, which only works together with --host.";
+        let host_plays_first = |flag: &str| {
+            format!(
+                "{flag} does not work with --host. The host is a human and goes first as player 1, and the remote player is player 2."
+            )
+        };
+        let cases: &[(&[&str], &str)] = &[
+            (
+                &["--grace-period", "30"],
+                "--grace-period only works together with --host, --join or --server.",
+            ),
+            (
+                &["--stand-in", "minimax"],
+                "--stand-in only works together with --host, --join or --server.",
+            ),
+            (
+                &["--games-dir", "games"],
+                "--games-dir only works together with --web.",
+            ),
+            (
+                &["--host", "7777", "--join", "localhost:7777"],
+                "--host and --join can't be used together.",
+            ),
+            (
+                &["--web", "8080", "--self-play", "3"],
+                "--self-play and --web can't be used together.",
+            ),
+            (
+                &["--server", "localhost:7777", "--load", "game.txt"],
+                "--server and --load can't be used together.",
+            ),
+            (
+                &["--save", "game.txt", "--host", "7777"],
+                "--save and --host can't be used together.",
+            ),
+            (
+                &["--tui", "--self-play", "3"],
+                "--tui and --self-play can't be used together.",
+            ),
+            (
+                &["--tui", "--web", "8080"],
+                "--tui and --web can't be used together.",
+            ),
+            (
+                &["--join", "localhost:7777", "--width", "5"],
+                "--width and --join can't be used together.",
+            ),
+            (
+                &["--load", "game.txt", "--win-length", "3"],
+                "--win-length and --load can't be used together.",
+            ),
+            (
+                &["--symbol1", "o", "--symbol2", "o"],
+                "Both players can't play with o.",
+            ),
+            (
+                &["--web", "8080", "--name1", "Ann"],
+                "--name1 and --web can't be used together.",
+            ),
+            (
+                &["--self-play", "3", "--first", "2"],
+                "--first and --self-play can't be used together.",
+            ),
+            (
+                &["--self-play", "3", "--player1", "human"],
+                "--player1 has to be an AI in self-play mode.",
+            ),
+            (
+                &["--host", "7777", "--name2", "Bob"],
+                "--name2 and --host can't be used together.",
+            ),
+            (
+                &["--host", "7777", "--first", "2"],
+                &host_plays_first("--first 2"),
+            ),
+            (
+                &["--host", "7777", "--player1", "minimax"],
+                &host_plays_first("--player1"),
+            ),
+            (
+                &["--host", "7777", "--player2", "human"],
+                &host_plays_first("--player2"),
+            ),
+            (&["--player2", "remote"], remote_needs_host),
+            (
+                &["--web", "8080", "--seed", "1"],
+                "--seed and --web can't be used together.",
+            ),
+            (
+                &["--web", "8080", "--think-time", "100"],
+                "--think-time and --web can't be used together.",
+            ),
+            (
+                &["--load", "game.txt", "--think-time1", "100"],
+                "--think-time1 and --load can't be used together.",
+            ),
+            (
+                &["--web", "8080", "--think-time2", "100"],
+                "--think-time2 and --web can't be used together.",
+            ),
+        ];
+        for (args, message) in cases {
+            match parse("", args) {
+                Err(e) => assert_eq!(e.to_string(), *message, "{args:?}"),
+                Ok(_) => panic!("{args:?} should have been refused"),
+            }
+        }
+    }
+}
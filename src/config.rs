@@ -0,0 +1,423 @@
+//! The config file, with defaults for the things the game would otherwise ask about, or pick by itself.
+//!
+//! It is read from the file given with `--config`, or else from `tictactoe/config.toml` in `$XDG_CONFIG_HOME`,
+//! which is usually `~/.config`. Every setting can be left out, and whatever is given on the command line wins over
+//! the file. Board sizes, names, symbols and the AI difficulty are offered as the answers to the questions at the
+//! start of a game, so pressing Enter picks them.
+//!
+//! ```toml
+//! [board]
+//! width = 5
+//! height = 5
+//! win_length = 4
+//!
+//! # Player 1 is also you, when you host, join or play on a server. Player 2 is whoever plays the second seat.
+//! [player1]
+//! name = "Ann"
+//! symbol = "o"
+//! colour = "green"  # red, green, yellow, blue, magenta or cyan, for --style color and --tui
+//!
+//! [player2]
+//! name = "Bob"
+//! think_time_ms = 2000  # for this player only, if it is a minimax AI
+//!
+//! [ai]
+//! difficulty = "hard"  # easy, medium, hard or perfect
+//! think_time_ms = 500  # how long a minimax AI may think about each move
+//!
+//! [display]
+//! style = "unicode"  # ascii, unicode or color
+//!
+//! # The letter keys of the terminal UI. The arrow keys, Enter and Esc always work too.
+//! [keys]
+//! left = "h"
+//! down = "j"
+//! up = "k"
+//! right = "l"
+//! place = " "
+//! undo = "u"
+//! redo = "r"
+//! restart = "n"
+//! quit = "q"
+//! ```
+
+use crate::ai::Difficulty;
+use crate::board::{BOARD_MAX_WIDTH, BOARD_MIN_WIDTH};
+use crate::gamestate::MIN_WIN_LENGTH;
+use crate::render::{Colour, DEFAULT_COLOURS, Style};
+use crate::tui::KeyBindings;
+use serde::Deserialize;
+use std::cmp;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use toml::Spanned;
+
+/// The defaults from the config file. Anything not in the file is None, or the game's own default.
+#[derive(Clone, Default, Debug)]
+pub struct Config {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub win_length: Option<usize>,
+    /// In seat order
+    pub players: [PlayerDefaults; 2],
+    pub difficulty: Option<Difficulty>,
+    /// How long a minimax AI may think about each move
+    pub think_time: Option<Duration>,
+    pub style: Option<Style>,
+    pub keys: KeyBindings,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct PlayerDefaults {
+    pub name: Option<String>,
+    pub symbol: Option<char>,
+    pub colour: Option<Colour>,
+    /// How long this player may think about each move, if it is a minimax AI. Wins over `Config::think_time`.
+    pub think_time: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    /// The file is not valid TOML, or a setting in it is not one we can use. Holds the line, if we know it.
+    Invalid(PathBuf, Option<usize>, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Could not read {}: {e}", path.display()),
+            ConfigError::Invalid(path, Some(line), message) => {
+                write!(f, "{}, line {line}: {message}", path.display())
+            }
+            ConfigError::Invalid(path, None, message) => {
+                write!(f, "{}: {message}", path.display())
+            }
+        }
+    }
+}
+
+/// The file as it is written, with where each setting is, so problems can be pointed out by line
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    board: BoardSection,
+    player1: PlayerSection,
+    player2: PlayerSection,
+    ai: AiSection,
+    display: DisplaySection,
+    keys: KeysSection,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct BoardSection {
+    width: Option<Spanned<usize>>,
+    height: Option<Spanned<usize>>,
+    win_length: Option<Spanned<usize>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PlayerSection {
+    name: Option<String>,
+    symbol: Option<Spanned<char>>,
+    colour: Option<Colour>,
+    think_time_ms: Option<Spanned<u64>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct AiSection {
+    difficulty: Option<Difficulty>,
+    think_time_ms: Option<Spanned<u64>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct DisplaySection {
+    style: Option<Style>,
+}
+
+/// The `[keys]` table. Each key keeps where it is, so a key bound twice can be pointed out on its own line.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct KeysSection {
+    left: Option<Spanned<char>>,
+    down: Option<Spanned<char>>,
+    up: Option<Spanned<char>>,
+    right: Option<Spanned<char>>,
+    place: Option<Spanned<char>>,
+    undo: Option<Spanned<char>>,
+    redo: Option<Spanned<char>>,
+    restart: Option<Spanned<char>>,
+    quit: Option<Spanned<char>>,
+}
+
+impl KeysSection {
+    /// The bindings, with the defaults for any key that is left out
+    fn bindings(&self) -> KeyBindings {
+        let defaults = KeyBindings::default();
+        let key = |given: &Option<Spanned<char>>, default: char| {
+            given.as_ref().map_or(default, |key| *key.get_ref())
+        };
+        KeyBindings {
+            left: key(&self.left, defaults.left),
+            down: key(&self.down, defaults.down),
+            up: key(&self.up, defaults.up),
+            right: key(&self.right, defaults.right),
+            place: key(&self.place, defaults.place),
+            undo: key(&self.undo, defaults.undo),
+            redo: key(&self.redo, defaults.redo),
+            restart: key(&self.restart, defaults.restart),
+            quit: key(&self.quit, defaults.quit),
+        }
+    }
+
+    /// Where each key is in the file, if it's there, in the same order as `KeyBindings::named`
+    fn spans(&self) -> [Option<Range<usize>>; 9] {
+        [
+            &self.left,
+            &self.down,
+            &self.up,
+            &self.right,
+            &self.place,
+            &self.undo,
+            &self.redo,
+            &self.restart,
+            &self.quit,
+        ]
+        .map(|key| key.as_ref().map(|key| key.span()))
+    }
+}
+
+impl Config {
+    /// The colours of the players' symbols, in seat order
+    pub fn colours(&self) -> [Colour; 2] {
+        [0, 1].map(|seat| self.players[seat].colour.unwrap_or(DEFAULT_COLOURS[seat]))
+    }
+}
+
+/// Where the config file is looked for when `--config` is not given
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("tictactoe").join("config.toml"))
+}
+
+/// Reads the config file at `path`, or at the default path if there is none.
+///
+/// There doesn't have to be a file at the default path, and then there are no defaults. A file that was asked for
+/// has to be there, though.
+pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+    let (path, required) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            read(&text).map_err(|(line, message)| ConfigError::Invalid(path, line, message))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => Ok(Config::default()),
+        Err(e) => Err(ConfigError::Io(path, e)),
+    }
+}
+
+/// Reads a config file from its text. Problems come with the line they are on, if we know it.
+pub fn read(text: &str) -> Result<Config, (Option<usize>, String)> {
+    let file: File = toml::from_str(text).map_err(|e| {
+        (
+            e.span().map(|span| line_of(text, span)),
+            e.message().to_string(),
+        )
+    })?;
+    let invalid = |span: Range<usize>, message: String| (Some(line_of(text, span)), message);
+
+    let board = &file.board;
+    for side in [&board.width, &board.height].into_iter().flatten() {
+        if !(BOARD_MIN_WIDTH..=BOARD_MAX_WIDTH).contains(side.get_ref()) {
+            return Err(invalid(
+                side.span(),
+                format!("The board sides go from {BOARD_MIN_WIDTH} to {BOARD_MAX_WIDTH}."),
+            ));
+        }
+    }
+    let width = board.width.as_ref().map(|width| *width.get_ref());
+    let height = board.height.as_ref().map(|height| *height.get_ref());
+    if let Some(win_length) = &board.win_length {
+        // If the board size is left to the command line or the questions, it's checked again once it's known
+        let longest_line = width.or(height).map_or(BOARD_MAX_WIDTH, |side| {
+            cmp::max(side, height.unwrap_or(side))
+        });
+        if !(MIN_WIN_LENGTH..=longest_line).contains(win_length.get_ref()) {
+            return Err(invalid(
+                win_length.span(),
+                format!("The win length has to be from {MIN_WIN_LENGTH} to {longest_line}."),
+            ));
+        }
+    }
+
+    let think_times = [
+        &file.ai.think_time_ms,
+        &file.player1.think_time_ms,
+        &file.player2.think_time_ms,
+    ];
+    for think_time in think_times.into_iter().flatten() {
+        if *think_time.get_ref() == 0 {
+            return Err(invalid(
+                think_time.span(),
+                "The AI needs at least 1 millisecond to think.".to_string(),
+            ));
+        }
+    }
+
+    let sections = [&file.player1, &file.player2];
+    for symbol in sections
+        .iter()
+        .filter_map(|section| section.symbol.as_ref())
+    {
+        if symbol.get_ref().is_whitespace() {
+            return Err(invalid(
+                symbol.span(),
+                "A symbol has to be something that shows up on the board.".to_string(),
+            ));
+        }
+    }
+    if let (Some(symbol_1), Some(symbol_2)) = (&file.player1.symbol, &file.player2.symbol)
+        && symbol_1.get_ref() == symbol_2.get_ref()
+    {
+        return Err(invalid(
+            symbol_2.span(),
+            format!("Both players can't play with {}.", symbol_2.get_ref()),
+        ));
+    }
+
+    let keys = file.keys.bindings();
+    let spans = file.keys.spans();
+    if let Some((first, second)) = keys.clash() {
+        let named = keys.named();
+        // The defaults are all different, so at least one of the two is in the file. The one further down is
+        // pointed out, since that is usually the one that was just added.
+        let span = [&spans[first], &spans[second]]
+            .into_iter()
+            .flatten()
+            .max_by_key(|span| span.start)
+            .cloned();
+        return Err((
+            span.map(|span| line_of(text, span)),
+            format!(
+                "'{}' is the key for both {} and {}.",
+                named[second].1, named[first].0, named[second].0
+            ),
+        ));
+    }
+
+    let player = |section: &PlayerSection| PlayerDefaults {
+        name: section.name.clone(),
+        symbol: section.symbol.as_ref().map(|symbol| *symbol.get_ref()),
+        colour: section.colour,
+        think_time: section
+            .think_time_ms
+            .as_ref()
+            .map(|think_time| Duration::from_millis(*think_time.get_ref())),
+    };
+    Ok(Config {
+        width,
+        height,
+        win_length: board
+            .win_length
+            .as_ref()
+            .map(|win_length| *win_length.get_ref()),
+        players: [player(&file.player1), player(&file.player2)],
+        difficulty: file.ai.difficulty,
+        think_time: file
+            .ai
+            .think_time_ms
+            .map(|think_time| Duration::from_millis(think_time.into_inner())),
+        style: file.display.style,
+        keys,
+    })
+}
+
+/// The line that `span` starts on, counting from 1
+fn line_of(text: &str, span: Range<usize>) -> usize {
+    text[..span.start.min(text.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line and message `read` refuses `text` with
+    fn refusal(text: &str) -> (Option<usize>, String) {
+        match read(text) {
+            Err(refusal) => refusal,
+            Ok(_) => panic!("{text:?} should have been refused"),
+        }
+    }
+
+    #[test]
+    fn reads_every_section() {
+        let text = "[board]\nwidth = 7\nheight = 6\nwin_length = 4\n\n[player2]\nsymbol = \"o\"\n\n[keys]\nundo = \"z\"\n";
+        let config = read(text).unwrap();
+        assert_eq!(
+            (config.width, config.height, config.win_length),
+            (Some(7), Some(6), Some(4))
+        );
+        assert_eq!(config.players[1].symbol, Some('o'));
+        assert_eq!(config.keys.undo, 'z');
+        assert_eq!(config.keys.left, KeyBindings::default().left);
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_refused() {
+        let (line, message) = refusal("[board]\nwidth = 5\nheight = \"six\"\n");
+        assert_eq!(line, Some(3));
+        assert!(message.contains("invalid type"), "{message}");
+
+        let (line, _) = refusal("[player1]\nname = \"Ann\"\nsymbol = \"xo\"\n");
+        assert_eq!(line, Some(3));
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        let (line, message) = refusal("[board]\nwidth = 5\nwidht = 6\n");
+        assert_eq!(line, Some(3));
+        assert!(message.contains("unknown field `widht`"), "{message}");
+
+        let (line, message) = refusal("[ai]\ndifficulty = \"hard\"\n\n[keys]\njump = \"x\"\n");
+        assert_eq!(line, Some(5));
+        assert!(message.contains("unknown field `jump`"), "{message}");
+
+        let (line, message) = refusal("[board]\nwidth = 5\n\n[sound]\nvolume = 3\n");
+        assert_eq!(line, Some(4));
+        assert!(message.contains("unknown field `sound`"), "{message}");
+    }
+
+    #[test]
+    fn keys_bound_twice_are_pointed_out_where_they_are() {
+        let (line, message) = refusal("[keys]\nleft = \"a\"\nup = \"w\"\nundo = \"a\"\n");
+        assert_eq!(line, Some(4));
+        assert_eq!(message, "'a' is the key for both left and undo.");
+
+        // The one further down the file is pointed out, whichever order the bindings are in
+        let (line, message) = refusal("[keys]\nquit = \"x\"\n\nleft = \"x\"\n");
+        assert_eq!(line, Some(4));
+        assert_eq!(message, "'x' is the key for both left and quit.");
+
+        // A key that is already taken by a default
+        let (line, message) = refusal("[display]\nstyle = \"ascii\"\n\n[keys]\nredo = \"h\"\n");
+        assert_eq!(line, Some(5));
+        assert_eq!(message, "'h' is the key for both left and redo.");
+    }
+}
@@ -1,18 +1,52 @@
 use crate::board::{Board, BoardError};
 use std::fmt::Display;
+use std::path::PathBuf;
 
 use crate::GameState;
 
 pub enum InputType {
     Coord(usize),
     Exit,
+    /// The player gives up the game, e.g. by not coming back in time after their connection dropped, and the other
+    /// player wins it
+    Forfeit,
     Restart,
     Help,
+    /// Take back the last move, along with the AI's reply to it
+    Undo,
+    /// Play a move that was taken back again
+    Redo,
+    /// Save the game to a file
+    Save(PathBuf),
+    /// Stop this game, and go on with one saved in a file
+    Load(PathBuf),
+    /// The game went on without us, e.g. while we were reconnecting to a remote player. These are all the moves of the current game.
+    Resync(Vec<usize>),
+}
+
+/// Something that happened in the game, which the other players may need to hear about, e.g. when they sit at another computer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    Moved(usize),
+    Restarted,
+    Exited,
+    /// A player answered whether they want to play again
+    Answered(bool),
 }
 
 pub enum InputError {
     InvalidCommand,
     InvalidBoardError(BoardError),
+    /// A coordinate named a row that is not on the board. Holds what was typed, and which rows there are.
+    InvalidRow(String, String),
+    /// A coordinate named a column that is not on the board. Holds what was typed, and which columns there are.
+    InvalidColumn(String, String),
+    /// A coordinate named a cell that already has a piece on it
+    CellTaken(String),
+    /// A coordinate had a column, but no row
+    MissingRow,
+    /// A coordinate had a row, but no column
+    MissingColumn,
 }
 
 impl Display for InputError {
@@ -22,6 +56,21 @@ impl Display for InputError {
             InputError::InvalidBoardError(e) => {
                 write!(f, "{e}")
             }
+            InputError::InvalidRow(row, rows) => {
+                write!(f, "There is no row {row}. The rows go from {rows}.")
+            }
+            InputError::InvalidColumn(col, cols) => {
+                write!(f, "There is no column {col}. The columns go from {cols}.")
+            }
+            InputError::CellTaken(cell) => write!(f, "{cell} is already taken."),
+            InputError::MissingRow => write!(
+                f,
+                "Which row? Type a coordinate like b2, 2,3 or 'row 2 col 3'."
+            ),
+            InputError::MissingColumn => write!(
+                f,
+                "Which column? Type a coordinate like b2, 2,3 or 'row 2 col 3'."
+            ),
         }
     }
 }
@@ -34,4 +83,10 @@ pub trait InputController {
 pub trait PlayerController {
     fn handle_input(&self, gamestate: &GameState) -> Result<InputType, InputError>;
     fn get_yes_no(&self) -> Result<bool, InputError>;
+    /// An optional remark shown after the player has made a move, e.g. how hard the AI had to think
+    fn describe_last_move(&self) -> Option<String> {
+        None
+    }
+    /// Called when one of the other players did something. Most players can see that on the board, so by default we ignore it.
+    fn observe(&self, _event: GameEvent) {}
 }
@@ -0,0 +1,155 @@
+//! Naming cells the way people do, rather than by their index: a column letter and a row number like `b2`, a row
+//! and a column like `2,3`, or spelled out as `row 2 col 3`.
+//!
+//! Rows and columns count from 1 at the top left, and the columns are lettered from `a`.
+
+use crate::board::Board;
+use crate::controller::InputError;
+
+/// The letter of the column at `col`, counting from 0. Boards are never wider than the alphabet.
+pub fn column_label(col: usize) -> char {
+    (b'a' + col as u8) as char
+}
+
+/// The name of the cell at `index`, e.g. `b2` for the middle of a 3x3 board
+pub fn cell_label(board: &Board, index: usize) -> String {
+    format!(
+        "{}{}",
+        column_label(index % board.width()),
+        index / board.width() + 1
+    )
+}
+
+/// Reads a cell given as a coordinate, and returns its index.
+///
+/// Returns None if `input` does not look like a coordinate at all, so it can be something else, like a command.
+/// Whether the cell is free is up to the caller.
+pub fn parse(input: &str, board: &Board) -> Option<Result<usize, InputError>> {
+    let input = input.trim().to_lowercase();
+    if let Some(rest) = input.strip_prefix("row") {
+        let (row, col) = match rest.split_once("col") {
+            Some((row, col)) => (row, col.strip_prefix("umn").unwrap_or(col)),
+            None => (rest, ""),
+        };
+        return Some(index_of(
+            board,
+            row.trim().trim_end_matches(','),
+            Column::Number(col.trim()),
+        ));
+    }
+    if let Some((row, col)) = input.split_once(',') {
+        return Some(index_of(board, row.trim(), Column::Number(col.trim())));
+    }
+    let mut characters = input.chars();
+    let letter = characters.next()?;
+    let row = characters.as_str();
+    if letter.is_ascii_alphabetic() && row.chars().all(|c| c.is_ascii_digit()) {
+        // A letter on its own is only a column if the board has it, and could be anything else otherwise
+        if row.is_empty() && !(0..board.width()).any(|col| column_label(col) == letter) {
+            return None;
+        }
+        return Some(index_of(board, row, Column::Letter(letter)));
+    }
+    None
+}
+
+/// How a coordinate names its column
+enum Column<'a> {
+    Letter(char),
+    Number(&'a str),
+}
+
+fn index_of(board: &Board, row: &str, col: Column) -> Result<usize, InputError> {
+    if row.is_empty() {
+        return Err(InputError::MissingRow);
+    }
+    let row = match row.parse::<usize>() {
+        Ok(number) if (1..=board.height()).contains(&number) => number - 1,
+        _ => {
+            return Err(InputError::InvalidRow(
+                row.to_string(),
+                format!("1 to {}", board.height()),
+            ));
+        }
+    };
+    let col = match col {
+        Column::Letter(letter) => (0..board.width())
+            .find(|&col| column_label(col) == letter)
+            .ok_or_else(|| {
+                InputError::InvalidColumn(
+                    letter.to_string(),
+                    format!("a to {}", column_label(board.width() - 1)),
+                )
+            })?,
+        Column::Number("") => return Err(InputError::MissingColumn),
+        Column::Number(col) => match col.parse::<usize>() {
+            Ok(number) if (1..=board.width()).contains(&number) => number - 1,
+            _ => {
+                return Err(InputError::InvalidColumn(
+                    col.to_string(),
+                    format!("1 to {}", board.width()),
+                ));
+            }
+        },
+    };
+    Ok(row * board.width() + col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_way_of_naming_a_cell() {
+        let board = Board::new(3, 3).unwrap();
+        for input in [
+            "b3",
+            "B3",
+            "3,2",
+            " 3, 2 ",
+            "row 3 col 2",
+            "row 3, column 2",
+        ] {
+            assert!(matches!(parse(input, &board), Some(Ok(7))), "{input}");
+        }
+        assert_eq!(cell_label(&board, 7), "b3");
+    }
+
+    #[test]
+    fn asks_for_what_is_missing() {
+        let board = Board::new(3, 3).unwrap();
+        for input in ["b", "row", "row col 2"] {
+            assert!(
+                matches!(parse(input, &board), Some(Err(InputError::MissingRow))),
+                "{input}"
+            );
+        }
+        for input in ["2,", "row 2", "row 2 col"] {
+            assert!(
+                matches!(parse(input, &board), Some(Err(InputError::MissingColumn))),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_other_input_alone() {
+        let board = Board::new(3, 3).unwrap();
+        for input in ["z", "help", "undo", ""] {
+            assert!(parse(input, &board).is_none(), "{input}");
+        }
+    }
+
+    #[test]
+    fn rejects_cells_off_the_board() {
+        let board = Board::new(3, 3).unwrap();
+        assert!(matches!(
+            parse("d1", &board),
+            Some(Err(InputError::InvalidColumn(col, cols))) if col == "d" && cols == "a to c"
+        ));
+        assert!(matches!(
+            parse("4,1", &board),
+            Some(Err(InputError::InvalidRow(row, rows))) if row == "4" && rows == "1 to 3"
+        ));
+    }
+}
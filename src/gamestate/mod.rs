@@ -1,29 +1,70 @@
 use crate::board::*;
 use crate::controller::*;
-use crate::player::base_player::PlayerPiece;
+use crate::player::base_player::{Player, PlayerPiece};
 use crate::player::playerlist::*;
+use crate::render::{self, Renderer};
+use crate::savefile;
+use std::path::PathBuf;
+use std::time::SystemTime;
 
-pub struct GameState<'a> {
+pub struct GameState {
     board: Board,
     referee: GameReferee,
     exit_wanted: bool,
     restart_wanted: bool,
-    players: PlayerList<'a>,
+    players: PlayerList,
+    /// The index of the player whose turn it is, in the order of `players`
+    current_player: usize,
+    /// The moves made so far in the current game, in order
+    history: Vec<Move>,
+    /// The moves taken back with undo, with the most recently taken back last. Redo plays them again.
+    undone: Vec<Move>,
+    /// Where the game is saved after every turn, if anywhere
+    autosave: Option<PathBuf>,
+    /// How the games played so far have ended
+    score: Score,
 }
 
-impl GameState<'_> {
-    pub fn new<'a>() -> GameState<'a> {
+/// A move made in the game, and who made it when
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub piece: PlayerPiece,
+    pub index: usize,
+    pub played_at: SystemTime,
+}
+
+/// How many games each player has won since the game loop started, and how many were drawn
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Score {
+    /// In the order the players move
+    pub wins: [u32; 2],
+    pub draws: u32,
+}
+
+impl GameState {
+    pub fn new(board: Board, referee: GameReferee, players: PlayerList) -> GameState {
         GameState {
-            board: Board::new(),
-            referee: GameReferee::default(),
-            players: PlayerList::default(),
+            board,
+            referee,
+            players,
             restart_wanted: false,
             exit_wanted: false,
+            current_player: 0,
+            history: Vec::new(),
+            undone: Vec::new(),
+            autosave: None,
+            score: Score::default(),
         }
     }
 
+    /// Starts a new game on an empty board, with the same players and rules
     pub fn restart(&mut self) {
-        *self = GameState::new();
+        self.board.clear();
+        self.restart_wanted = false;
+        self.exit_wanted = false;
+        self.current_player = 0;
+        self.history.clear();
+        self.undone.clear();
     }
 
     pub fn board(&self) -> &Board {
@@ -34,37 +75,281 @@ impl GameState<'_> {
         &self.referee
     }
 
-    pub fn game_loop(&mut self) {
+    /// Plays until someone wants to stop, drawing the board with `renderer`
+    pub fn game_loop(&mut self, renderer: &dyn Renderer) {
         while !self.exit_wanted {
-            self.board.render(self);
-            self.process_turn();
+            render::print_board(renderer, &self.board, &self.players);
+            self.process_turn(renderer);
         }
     }
 
+    /// Saves the game where `autosave_to` said, if anywhere. A finished game has nothing left to pick up, so the last
+    /// save before the end is kept.
+    fn autosave(&self) {
+        if let Some(path) = &self.autosave
+            && self.outcome().is_none()
+            && let Err(e) = savefile::save(self, path)
+        {
+            println!("{e}");
+        }
+    }
+
+    /// Saves the game to `path` after every turn, so it can be picked up again if the program is stopped. Taking moves
+    /// back, playing them again and starting over count as turns too. Finished games are not saved.
+    pub fn autosave_to(&mut self, path: PathBuf) {
+        self.autosave = Some(path);
+    }
+
     pub fn players(&self) -> &PlayerList {
         &self.players
     }
 
-    fn process_turn(&mut self) {
-        for player in self.players.iter() {
+    /// Hands every local player a new controller made by `make`, e.g. to play in the terminal UI instead of typing
+    pub fn set_local_controllers(&mut self, make: impl Fn() -> Box<dyn PlayerController>) {
+        self.players.set_local_controllers(make);
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// The player whose turn it is
+    pub fn current_player(&self) -> &Player {
+        self.players
+            .iter()
+            .nth(self.current_player)
+            .expect("The current player is always in the player list")
+    }
+
+    /// The piece of the player whose turn it is
+    pub fn piece_to_move(&self) -> PlayerPiece {
+        self.current_player().player_piece
+    }
+
+    /// The moves made so far in the current game, in order
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// The cells played so far in the current game, in order
+    pub fn moves(&self) -> Vec<usize> {
+        self.history.iter().map(|played| played.index).collect()
+    }
+
+    /// Places the current player's piece at `index`, and passes the turn on to the next player
+    pub fn place_piece(&mut self, index: usize) -> Result<(), BoardError> {
+        let piece = self.piece_to_move();
+        self.play(index, SystemTime::now())?;
+        // A new move makes a different game from the one that was undone, so there is nothing left to redo
+        self.undone.clear();
+        self.notify_players_except(piece, GameEvent::Moved(index));
+        Ok(())
+    }
+
+    /// Takes back the last move made by a human, along with the AI replies to it, so it's their turn again.
+    /// Returns false if there was nothing to take back.
+    pub fn undo(&mut self) -> bool {
+        let Some(last_human_move) = self.last_human_move() else {
+            return false;
+        };
+        while self.history.len() > last_human_move {
+            let played = self
+                .history
+                .pop()
+                .expect("There are moves left to take back");
+            self.board.modify_at_cell(played.index, CellState::Empty);
+            self.current_player =
+                (self.current_player + self.players.len() - 1) % self.players.len();
+            self.undone.push(played);
+        }
+        true
+    }
+
+    /// Whether `undo` has a move to take back
+    pub fn can_undo(&self) -> bool {
+        self.last_human_move().is_some()
+    }
+
+    /// Whether `redo` has a move to play again
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Where in the history the last move made by a human is
+    fn last_human_move(&self) -> Option<usize> {
+        self.history
+            .iter()
+            .rposition(|played| !self.player_with(played.piece).is_ai())
+    }
+
+    /// Plays the last move taken back by `undo` again, along with the AI replies to it.
+    /// Returns false if there was nothing to play again.
+    pub fn redo(&mut self) -> bool {
+        let Some(played) = self.undone.pop() else {
+            return false;
+        };
+        self.play(played.index, SystemTime::now())
+            .expect("A move that was taken back fits on the board again");
+        while self.current_player().is_ai()
+            && let Some(reply) = self.undone.pop()
+        {
+            self.play(reply.index, SystemTime::now())
+                .expect("A move that was taken back fits on the board again");
+        }
+        true
+    }
+
+    /// Starts the current game over, and plays `moves` without telling the players about them, since they already know
+    pub fn replay(&mut self, moves: &[usize]) -> Result<(), BoardError> {
+        self.restart();
+        for &index in moves {
+            self.play(index, SystemTime::now())?;
+        }
+        Ok(())
+    }
+
+    /// Plays a move that was made at `played_at`, e.g. in a saved game, without telling the players about it
+    pub fn replay_move(&mut self, index: usize, played_at: SystemTime) -> Result<(), BoardError> {
+        self.play(index, played_at)
+    }
+
+    fn play(&mut self, index: usize, played_at: SystemTime) -> Result<(), BoardError> {
+        let piece = self.piece_to_move();
+        self.board.is_valid_move(index)?;
+        self.board.place(index, piece)?;
+        self.history.push(Move {
+            piece,
+            index,
+            played_at,
+        });
+        self.current_player = (self.current_player + 1) % self.players.len();
+        Ok(())
+    }
+
+    fn player_with(&self, piece: PlayerPiece) -> &Player {
+        self.players
+            .iter()
+            .find(|player| player.player_piece == piece)
+            .expect("Every piece on the board belongs to a player")
+    }
+
+    /// Tells every player but the one with `piece` what happened
+    pub fn notify_players_except(&self, piece: PlayerPiece, event: GameEvent) {
+        for player in self.players.iter().filter(|p| p.player_piece != piece) {
+            player.controller.observe(event);
+        }
+    }
+
+    /// Returns how the game ended, or None if it is still going
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        if let Some(winning_piece) = self.referee.adjudicate(&self.board) {
+            Some(GameOutcome::Winner(winning_piece))
+        } else if self.board.is_full() {
+            Some(GameOutcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    fn process_turn(&mut self, renderer: &dyn Renderer) {
+        for _ in 0..self.players.len() {
+            // Set when whose turn it is no longer follows from the order we were going in, e.g. after an undo
+            let mut turn_order_changed = false;
+            if self.current_player().is_ai() {
+                println!("{} is thinking...", self.current_player().name);
+            } else if self.current_player().is_remote() {
+                println!("Waiting for {} to move...", self.current_player().name);
+            }
             // Inner loop to ensure player provides correct input
             'inputloop: loop {
+                let player = self.current_player();
                 match player.controller.handle_input(self) {
                     Ok(InputType::Help) => {
-                        self.board.render_help();
+                        render::print_help(renderer, &self.board);
                     }
                     Ok(InputType::Coord(coord)) => {
-                        let _ = self.board.place(coord, player.player_piece);
+                        if let Some(description) = player.controller.describe_last_move() {
+                            println!("{description}");
+                        }
+                        if let Err(e) = self.place_piece(coord) {
+                            println!("{e}");
+                            continue 'inputloop;
+                        }
                         break;
                     }
                     Ok(InputType::Exit) => {
+                        self.notify_players_except(self.piece_to_move(), GameEvent::Exited);
+                        self.exit_wanted = true;
+                        break;
+                    }
+                    Ok(InputType::Forfeit) => {
+                        // Everyone else wins, which in a game for two is the other player
+                        let winner = (self.current_player + 1) % self.players.len();
+                        self.score.wins[winner] += 1;
+                        if let Some(player) = self.players.iter().nth(winner) {
+                            println!("{} is the winner!", player.name);
+                        }
+                        self.notify_players_except(self.piece_to_move(), GameEvent::Exited);
                         self.exit_wanted = true;
                         break;
                     }
                     Ok(InputType::Restart) => {
+                        self.notify_players_except(self.piece_to_move(), GameEvent::Restarted);
                         self.restart_wanted = true;
                         break;
                     }
+                    Ok(InputType::Undo | InputType::Redo)
+                        if self.players.iter().any(Player::is_remote) =>
+                    {
+                        // The other computer would not know about it, and the two games would drift apart
+                        println!("Moves can't be taken back in a network game.");
+                    }
+                    Ok(InputType::Undo) => {
+                        if !self.undo() {
+                            println!("There is nothing to undo.");
+                            continue 'inputloop;
+                        }
+                        // Whose turn it is now depends on how many moves were taken back
+                        turn_order_changed = true;
+                        break;
+                    }
+                    Ok(InputType::Redo) => {
+                        if !self.redo() {
+                            println!("There is nothing to redo.");
+                            continue 'inputloop;
+                        }
+                        turn_order_changed = true;
+                        break;
+                    }
+                    Ok(InputType::Save(path)) => match savefile::save(self, &path) {
+                        Ok(()) => println!("Saved the game to {}.", path.display()),
+                        Err(e) => println!("{e}"),
+                    },
+                    Ok(InputType::Load(_)) if self.players.iter().any(Player::is_remote) => {
+                        println!("You can't load another game in the middle of a network game.");
+                    }
+                    Ok(InputType::Load(path)) => match savefile::load(&path) {
+                        Ok(loaded) => {
+                            let autosave = self.autosave.take();
+                            *self = loaded;
+                            self.autosave = autosave;
+                            println!("Loaded the game from {}.", path.display());
+                            turn_order_changed = true;
+                            break;
+                        }
+                        Err(e) => println!("{e}"),
+                    },
+                    Ok(InputType::Resync(moves)) => {
+                        // The moves are decided on the other computer, so if we can't follow them we can't go on playing
+                        if let Err(e) = self.replay(&moves) {
+                            println!("{e}");
+                            self.notify_players_except(self.piece_to_move(), GameEvent::Exited);
+                            self.exit_wanted = true;
+                        }
+                        // Whoever moves next is decided by the moves, not by the order we were going in
+                        turn_order_changed = true;
+                        break;
+                    }
                     Err(e) => {
                         println!("{e}");
                         // We want the player(s) to be able to rectify their choice and provide true input
@@ -72,7 +357,11 @@ impl GameState<'_> {
                     }
                 }
             }
-            if self.board.is_full() {
+            if !self.restart_wanted {
+                self.autosave();
+            }
+            // Once the game is over, nobody else gets to move
+            if self.outcome().is_some() || turn_order_changed {
                 break;
             }
 
@@ -83,11 +372,24 @@ impl GameState<'_> {
 
         if self.restart_wanted {
             self.restart();
+            self.autosave();
         }
 
+        match self.outcome() {
+            Some(GameOutcome::Winner(piece)) => {
+                let position = self
+                    .players
+                    .iter()
+                    .position(|player| player.player_piece == piece)
+                    .expect("Every piece on the board belongs to a player");
+                self.score.wins[position] += 1;
+            }
+            Some(GameOutcome::Draw) => self.score.draws += 1,
+            None => {}
+        }
         if let Some(winner) = self.we_have_winner() {
             println!("{winner} is the winner!");
-            self.board.render(self);
+            render::print_board(renderer, &self.board, &self.players);
             self.post_game_loop();
         } else if self.board.is_full() {
             println!("A draw.");
@@ -100,7 +402,7 @@ impl GameState<'_> {
             let mut winner_name: &str = "";
             for p in self.players.iter() {
                 if winning_piece == p.player_piece {
-                    winner_name = p.name;
+                    winner_name = &p.name;
                 }
             }
             Some(winner_name)
@@ -109,33 +411,75 @@ impl GameState<'_> {
         }
     }
 
+    /// Asks every human player whether they want another game. We only restart if all of them do.
     fn post_game_loop(&mut self) {
-        'inputloop: loop {
-            println!("Would you like to restart? (Y/N)");
-            let choice = self
-                .players
-                .get_local_human_player()
-                .controller
-                .get_yes_no();
-            match choice {
-                Ok(true) => {
-                    self.restart();
-                    break;
+        let human_players: Vec<&Player> = self.players.get_human_players().collect();
+        // If nobody is playing from a keyboard, e.g. when two AIs play each other, there is nobody to ask
+        let mut restart_wanted = !human_players.is_empty();
+        for human_player in &human_players {
+            'inputloop: loop {
+                if human_player.is_remote() {
+                    println!(
+                        "Waiting for {} to decide whether to play again...",
+                        human_player.name
+                    );
+                } else if human_players.len() > 1 {
+                    println!("{}, would you like to restart? (Y/N)", human_player.name);
+                } else {
+                    println!("Would you like to restart? (Y/N)");
                 }
-                Ok(false) => {
-                    self.exit_wanted = true;
-                    break;
+                match human_player.controller.get_yes_no() {
+                    Ok(choice) => {
+                        restart_wanted = choice;
+                        break;
+                    }
+                    _ => continue 'inputloop,
                 }
-                _ => continue 'inputloop,
             }
+            self.notify_players_except(
+                human_player.player_piece,
+                GameEvent::Answered(restart_wanted),
+            );
+            if !restart_wanted {
+                break;
+            }
+        }
+
+        if restart_wanted {
+            self.restart();
+        } else {
+            self.exit_wanted = true;
         }
     }
 }
 
-#[derive(Default)]
-pub(crate) struct GameReferee {}
+/// How a finished game ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameOutcome {
+    Winner(PlayerPiece),
+    Draw,
+}
+
+/// The smallest number of pieces in a row we allow to count as a win
+pub const MIN_WIN_LENGTH: usize = 3;
+/// Wins longer than this are not offered by default, since that's what Gomoku uses even on huge boards
+pub const MAX_DEFAULT_WIN_LENGTH: usize = 5;
+
+#[derive(Clone, Copy)]
+pub struct GameReferee {
+    win_length: usize,
+}
 
 impl GameReferee {
+    /// Creates a referee that declares a winner once a player has `win_length` pieces in an unbroken line
+    pub fn new(win_length: usize) -> GameReferee {
+        GameReferee { win_length }
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
     /// Checks to see if there is a winner on the Board, and returns the winning piece if so
     // TODO: It would be more semantic to return a Player, rather than PlayerPiece
     pub fn adjudicate(&self, board: &Board) -> Option<PlayerPiece> {
@@ -144,6 +488,38 @@ impl GameReferee {
             .or_else(|| self.diagonals_have_winner(board))
     }
 
+    /// Checks whether the piece at `index` is part of a winning line, and returns that piece if so.
+    ///
+    /// This only looks at the lines going through `index`, so it is a lot cheaper than `adjudicate` when we know which move was made last.
+    pub fn adjudicate_move(&self, board: &Board, index: usize) -> Option<PlayerPiece> {
+        let (row, col) = (
+            (index / board.width()) as isize,
+            (index % board.width()) as isize,
+        );
+        let Some(CellState::Player(player_piece)) = board.get_cell(row, col) else {
+            return None;
+        };
+        // Horizontal, vertical, major diagonal and minor diagonal
+        let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        let count_in_direction = |(row_step, col_step): (isize, isize)| {
+            (1..)
+                .take_while(|&n| {
+                    board.get_cell(row + n * row_step, col + n * col_step)
+                        == Some(CellState::Player(player_piece))
+                })
+                .count()
+        };
+        directions
+            .into_iter()
+            .any(|(row_step, col_step)| {
+                let line_length = 1
+                    + count_in_direction((row_step, col_step))
+                    + count_in_direction((-row_step, -col_step));
+                line_length >= self.win_length
+            })
+            .then_some(player_piece)
+    }
+
     fn rows_have_winner(&self, board: &Board) -> Option<PlayerPiece> {
         for row in board.get_all_rows() {
             let winner = self.has_winner(&row);
@@ -166,22 +542,225 @@ impl GameReferee {
     }
 
     fn diagonals_have_winner(&self, board: &Board) -> Option<PlayerPiece> {
-        self.has_winner(&board.get_diagonal(Diagonal::Major))
-            .or_else(|| self.has_winner(&board.get_diagonal(Diagonal::Minor)))
+        let mut diagonals = board
+            .get_all_diagonals(Diagonal::Major)
+            .into_iter()
+            .chain(board.get_all_diagonals(Diagonal::Minor));
+        diagonals.find_map(|diagonal| self.has_winner(&diagonal))
     }
 
     fn has_winner(&self, slice: &[CellState]) -> Option<PlayerPiece> {
-        // Just checking to see if the symbol on the first cell is the same as all symbols in all cells
-        if let Some(CellState::Player(player_piece)) = slice.first() {
-            if slice
-                .iter()
-                .all(|&board_cell| board_cell == CellState::Player(*player_piece))
+        // Sliding along the line, checking whether the first cell of each stretch of `win_length` cells matches all the others
+        slice.windows(self.win_length).find_map(|stretch| {
+            if let Some(CellState::Player(player_piece)) = stretch.first()
+                && stretch
+                    .iter()
+                    .all(|&board_cell| board_cell == CellState::Player(*player_piece))
             {
-                return Some(*player_piece);
+                Some(*player_piece)
             } else {
-                return None;
+                None
             }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AIStrategy;
+    use crate::ai::minimax::MinimaxConfig;
+
+    /// The cells of a line of `length` cells starting at `start` and going in `direction`
+    fn line(
+        size: usize,
+        start: (usize, usize),
+        direction: (isize, isize),
+        length: usize,
+    ) -> Vec<usize> {
+        (0..length as isize)
+            .map(|n| {
+                let row = start.0 as isize + n * direction.0;
+                let col = start.1 as isize + n * direction.1;
+                row as usize * size + col as usize
+            })
+            .collect()
+    }
+
+    /// Plays `cells` for player 1 on an empty board, and checks who the referee says has won
+    fn check(size: usize, win_length: usize, cells: &[usize], winner: Option<u8>) {
+        let mut board = Board::new(size, size).unwrap();
+        for &index in cells {
+            board.place(index, PlayerPiece::new(1)).unwrap();
         }
-        None
+        let referee = GameReferee::new(win_length);
+        let winner = winner.map(PlayerPiece::new);
+        assert_eq!(
+            referee.adjudicate(&board),
+            winner,
+            "{cells:?} on {size}x{size}"
+        );
+        for &index in cells {
+            assert_eq!(
+                referee.adjudicate_move(&board, index),
+                winner,
+                "{cells:?} on {size}x{size}, last move {index}"
+            );
+        }
+    }
+
+    #[test]
+    fn wins_across_the_whole_board() {
+        for size in 3..=7 {
+            for n in 0..size {
+                check(size, size, &line(size, (n, 0), (0, 1), size), Some(1));
+                check(size, size, &line(size, (0, n), (1, 0), size), Some(1));
+            }
+            check(size, size, &line(size, (0, 0), (1, 1), size), Some(1));
+            check(
+                size,
+                size,
+                &line(size, (0, size - 1), (1, -1), size),
+                Some(1),
+            );
+        }
+    }
+
+    #[test]
+    fn wins_on_short_lines() {
+        for size in 3..=7 {
+            // The shortest diagonals a win of 3 fits on, in the corners
+            let corner = size - 3;
+            check(size, 3, &line(size, (0, corner), (1, 1), 3), Some(1));
+            check(size, 3, &line(size, (corner, 0), (1, 1), 3), Some(1));
+            check(size, 3, &line(size, (0, 2), (1, -1), 3), Some(1));
+            check(
+                size,
+                3,
+                &line(size, (corner, size - 1), (1, -1), 3),
+                Some(1),
+            );
+            // Somewhere in the middle of a row and a column
+            check(size, 3, &line(size, (size - 1, corner), (0, 1), 3), Some(1));
+            check(size, 3, &line(size, (corner, size / 2), (1, 0), 3), Some(1));
+        }
+    }
+
+    #[test]
+    fn no_win_one_piece_short() {
+        for size in 3..=7 {
+            for direction in [(0, 1), (1, 0), (1, 1)] {
+                check(size, size, &line(size, (0, 0), direction, size - 1), None);
+            }
+            check(
+                size,
+                size,
+                &line(size, (0, size - 1), (1, -1), size - 1),
+                None,
+            );
+        }
+    }
+
+    #[test]
+    fn an_opponent_breaks_the_line() {
+        for size in 3..=7 {
+            let mut board = Board::new(size, size).unwrap();
+            let cells = line(size, (0, 0), (1, 1), size);
+            for (n, &index) in cells.iter().enumerate() {
+                let piece = if n == size / 2 { 2 } else { 1 };
+                board.place(index, PlayerPiece::new(piece)).unwrap();
+            }
+            let referee = GameReferee::new(size);
+            assert_eq!(referee.adjudicate(&board), None);
+            assert_eq!(referee.adjudicate_move(&board, cells[0]), None);
+        }
+    }
+
+    /// A 3x3 game where Ann, at the keyboard, plays against an AI, and goes first
+    fn game_against_ai() -> GameState {
+        let players = PlayerList::new(
+            Player::local("Ann", 'x', PlayerPiece::new(1)),
+            Player::ai(
+                "AI",
+                'y',
+                PlayerPiece::new(2),
+                AIStrategy::Minimax(MinimaxConfig::default()),
+            ),
+        );
+        GameState::new(Board::new(3, 3).unwrap(), GameReferee::new(3), players)
+    }
+
+    #[test]
+    fn undo_takes_back_the_ai_reply_too() {
+        let mut game = game_against_ai();
+        assert!(!game.can_undo());
+        for index in [4, 0, 8, 2] {
+            game.place_piece(index).unwrap();
+        }
+        assert!(game.undo());
+        assert_eq!(game.moves(), vec![4, 0]);
+        assert_eq!(*game.piece_to_move(), 1);
+        assert_eq!(game.board().get_cell(2, 2), Some(CellState::Empty));
+        assert_eq!(game.board().get_cell(0, 2), Some(CellState::Empty));
+
+        assert!(game.undo());
+        assert!(game.moves().is_empty());
+        assert!(!game.can_undo());
+        assert!(!game.undo());
+    }
+
+    #[test]
+    fn redo_plays_the_moves_that_were_taken_back() {
+        let mut game = game_against_ai();
+        for index in [4, 0, 8, 2] {
+            game.place_piece(index).unwrap();
+        }
+        game.undo();
+        game.undo();
+        assert!(game.redo());
+        assert_eq!(game.moves(), vec![4, 0]);
+        assert!(game.redo());
+        assert_eq!(game.moves(), vec![4, 0, 8, 2]);
+        assert_eq!(
+            game.board().get_cell(2, 2),
+            Some(CellState::Player(PlayerPiece::new(1)))
+        );
+        assert_eq!(*game.piece_to_move(), 1);
+        assert!(!game.can_redo());
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn a_new_move_clears_what_could_be_redone() {
+        let mut game = game_against_ai();
+        game.place_piece(4).unwrap();
+        game.place_piece(0).unwrap();
+        game.undo();
+        assert!(game.can_redo());
+        game.place_piece(8).unwrap();
+        assert!(!game.can_redo());
+        assert!(!game.redo());
+        assert_eq!(game.moves(), vec![8]);
+    }
+
+    #[test]
+    fn history_records_who_played_where_and_when() {
+        let mut game = game_against_ai();
+        let before = SystemTime::now();
+        game.place_piece(4).unwrap();
+        game.place_piece(0).unwrap();
+        let after = SystemTime::now();
+        let long_ago = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_760_000_000);
+        game.replay_move(8, long_ago).unwrap();
+
+        let history = game.history();
+        assert_eq!(history.len(), 3);
+        for (played, (piece, index)) in history.iter().zip([(1, 4), (2, 0), (1, 8)]) {
+            assert_eq!((*played.piece, played.index), (piece, index));
+        }
+        for played in &history[..2] {
+            assert!(before <= played.played_at && played.played_at <= after);
+        }
+        assert_eq!(history[2].played_at, long_ago);
     }
 }
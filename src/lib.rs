@@ -0,0 +1,18 @@
+pub mod ai;
+pub mod board;
+pub mod cli;
+pub mod config;
+pub mod controller;
+pub mod coordinates;
+pub mod gamestate;
+pub mod network;
+pub mod player;
+pub mod render;
+pub mod rng;
+pub mod savefile;
+pub mod selfplay;
+pub mod server;
+pub mod tui;
+pub mod web;
+
+use crate::gamestate::*;
@@ -1,14 +1,551 @@
-mod ai;
-mod board;
-mod controller;
-mod gamestate;
-mod player;
-
-use crate::gamestate::*;
+use std::cmp;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process;
+use tictactoe::ai::{AIStrategy, Difficulty};
+use tictactoe::board::{BOARD_MAX_WIDTH, BOARD_MIN_WIDTH, BOARD_STANDARD_WIDTH, Board};
+use tictactoe::cli::{self, CliOptions, PlayerChoice};
+use tictactoe::config::Config;
+use tictactoe::gamestate::*;
+use tictactoe::network::protocol::Message;
+use tictactoe::network::reconnect::Rejoin;
+use tictactoe::network::{self, NetworkError, lobby, spectator};
+use tictactoe::player::base_player::{Player, PlayerPiece, symbol_other_than};
+use tictactoe::player::playerlist::PlayerList;
+use tictactoe::render::Renderer;
+use tictactoe::savefile;
+use tictactoe::selfplay;
+use tictactoe::tui::TuiPlayer;
+use tictactoe::web;
 
 fn main() {
+    let options = cli::parse_args(std::env::args().skip(1)).unwrap_or_else(|e| exit_with_error(e));
+    if options.help_wanted {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if let Some(games) = options.self_play_games {
+        run_self_play(games, options);
+        return;
+    }
+    if let Some(port) = options.web_port {
+        let games_dir = options
+            .games_dir
+            .unwrap_or_else(|| PathBuf::from("tictactoe-games"));
+        web::run(port, &games_dir).unwrap_or_else(|e| exit_with_error(e));
+        return;
+    }
+
+    // Finding out halfway through setting up a game would be a shame
+    if options.tui && (!io::stdin().is_terminal() || !io::stdout().is_terminal()) {
+        exit_with_error("--tui needs to be run in a terminal.");
+    }
+
     println!("Welcome to tic tac toe.");
-    let mut game = GameState::new();
+    let renderer = options.style().renderer(options.config.colours());
+    if let Some(path) = &options.load_path {
+        let mut game = savefile::load(path).unwrap_or_else(|e| exit_with_error(e));
+        if let Some(save_path) = options.save_path.clone() {
+            game.autosave_to(save_path);
+        }
+        play(game, &options, &*renderer);
+        return;
+    }
+    if let Some(address) = &options.join_address {
+        let name = ask_name("What is your name?", &default_name(&options, 1, "Player 2"));
+        let symbol = ask_symbol(&name, default_symbols(&options)[1], None);
+        match network::join(address, &name, symbol, options.reconnect_policy()) {
+            Ok(game) => play(game, &options, &*renderer),
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+    if let Some(address) = &options.server_address {
+        let name = ask_name("What is your name?", &default_name(&options, 0, "Player"));
+        let symbol = ask_symbol(&name, default_symbols(&options)[0], None);
+        match visit_lobby(address, &name, symbol, &options, &*renderer) {
+            Ok(Some(game)) => play(game, &options, &*renderer),
+            Ok(None) => {}
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+
+    let board = pick_board(&options);
+    let win_length = pick_win_length(&options, &board);
+    if let Some(port) = options.host_port {
+        let name = options.name_1.clone().unwrap_or_else(|| {
+            ask_name("What is your name?", &default_name(&options, 0, "Player 1"))
+        });
+        let symbol = options
+            .symbol_1
+            .unwrap_or_else(|| ask_symbol(&name, default_symbols(&options)[0], None));
+        match network::host(
+            port,
+            board,
+            win_length,
+            &name,
+            symbol,
+            options.reconnect_policy(),
+        ) {
+            Ok(game) => play(game, &options, &*renderer),
+            Err(e) => exit_with_error(e),
+        }
+        return;
+    }
+    let players = if options.players_given() {
+        cli_players(&options)
+    } else {
+        ask_seating(&options)
+    };
+    let mut game = GameState::new(board, GameReferee::new(win_length), players);
+    if let Some(save_path) = options.save_path.clone() {
+        game.autosave_to(save_path);
+    }
+
+    play(game, &options, &*renderer);
+}
+
+/// Plays the game until someone wants to stop, in the terminal UI if it was asked for
+fn play(mut game: GameState, options: &CliOptions, renderer: &dyn Renderer) {
+    if options.tui {
+        let colours = options.config.colours();
+        game.set_local_controllers(|| Box::new(TuiPlayer::new(options.config.keys, colours)));
+    }
+    game.game_loop(renderer);
+}
+
+/// Lets the user look around the lobby of a game server, until they have created or joined a game.
+///
+/// Watching a game happens right here, so then there is no game left to play afterwards.
+fn visit_lobby(
+    address: &str,
+    name: &str,
+    symbol: char,
+    options: &CliOptions,
+    renderer: &dyn Renderer,
+) -> Result<Option<GameState>, NetworkError> {
+    let mut connection = network::connect(address, name, symbol)?;
+    loop {
+        println!(
+            "Type 'list' to see the games on the server, 'join <number>' to join one of them, 'watch <number>' to watch one, 'create' to create a game, or 'quick' to play against whoever has waited the longest."
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        let command = input.trim().to_lowercase();
+        let request = match command.split_once(' ') {
+            None if command == "list" => {
+                let (games, live_games) = lobby::list_games(&mut connection)?;
+                if games.is_empty() {
+                    println!("Nobody is waiting for an opponent right now.");
+                }
+                for game in games {
+                    println!(
+                        "{}: {} is waiting on a {}x{} board, {} in a row",
+                        game.id, game.name, game.width, game.height, game.win_length
+                    );
+                }
+                for live_game in live_games {
+                    println!("{}: {} (being played)", live_game.id, live_game.description);
+                }
+                continue;
+            }
+            None if command == "create" => {
+                let board = pick_board(options);
+                let win_length = pick_win_length(options, &board);
+                Message::Create {
+                    width: board.width(),
+                    height: board.height(),
+                    win_length,
+                }
+            }
+            None if command == "quick" => Message::Quick,
+            Some(("join", id)) if id.parse::<u32>().is_ok() => {
+                Message::Enter(id.parse().expect("Could not parse game number."))
+            }
+            Some(("watch", id)) if id.parse::<u32>().is_ok() => {
+                Message::Watch(id.parse().expect("Could not parse game number."))
+            }
+            _ => {
+                println!("{command} is not a valid choice.");
+                continue;
+            }
+        };
+        connection.send(&request)?;
+        // The server says no if e.g. someone else got to the game first, and then we are still in the lobby
+        match network::wait_for_rules(&mut connection) {
+            Ok((board, win_length)) if matches!(request, Message::Watch(_)) => {
+                spectator::watch(connection, board, win_length, renderer)?;
+                return Ok(None);
+            }
+            Ok((board, win_length)) => {
+                let rejoin = Rejoin::Dial(address.to_string());
+                return network::take_seat(
+                    connection,
+                    board,
+                    win_length,
+                    name,
+                    rejoin,
+                    options.reconnect_policy(),
+                )
+                .map(Some);
+            }
+            Err(NetworkError::Refused(reason)) => println!("{reason}"),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Pits two AIs against each other without asking anything, and prints how it went
+fn run_self_play(games: u32, mut options: CliOptions) {
+    let board = match (options.width, options.height) {
+        (None, None) => new_board(options.config.width, options.config.height),
+        (width, height) => new_board(width, height),
+    };
+    let win_length = match options.win_length {
+        Some(win_length) => {
+            cli::check_win_length(win_length, &board).unwrap_or_else(|e| exit_with_error(e))
+        }
+        None => configured_win_length(&options, &board),
+    };
+    let choices = [
+        (options.player_1.take(), options.name_1.take()),
+        (options.player_2.take(), options.name_2.take()),
+    ];
+    let mut seat = 0;
+    let contenders = choices.map(|(choice, name)| {
+        seat += 1;
+        let mut contender = match choice {
+            Some(PlayerChoice::AI(contender)) => contender,
+            _ => options.default_contender(seat - 1),
+        };
+        // Both sides may well use the same strategy, so unless they were named, we tell them apart by seat
+        contender.name = name.unwrap_or_else(|| format!("Player {seat} ({})", contender.name));
+        contender
+    });
+
+    let report = selfplay::play_matches(&contenders, games, &board, GameReferee::new(win_length));
+    print!("{report}");
+}
+
+/// Creates a board from the sizes given on the command line or in the config file, where the height defaults to the width
+fn new_board(width: Option<usize>, height: Option<usize>) -> Board {
+    let width = width.or(height).unwrap_or(BOARD_STANDARD_WIDTH);
+    Board::new(width, height.unwrap_or(width))
+        .expect("The command line and config file only allow valid board sizes")
+}
+
+/// The board from the command line, or else the one the user asks for
+fn pick_board(options: &CliOptions) -> Board {
+    match (options.width, options.height) {
+        (None, None) => ask_board(&options.config),
+        (width, height) => new_board(width, height),
+    }
+}
+
+/// The win length from the command line, or else the one the user asks for
+fn pick_win_length(options: &CliOptions, board: &Board) -> usize {
+    match options.win_length {
+        Some(win_length) => {
+            cli::check_win_length(win_length, board).unwrap_or_else(|e| exit_with_error(e))
+        }
+        None => ask_win_length(board, configured_win_length(options, board)),
+    }
+}
+
+/// The win length from the config file if it fits on the board, or else the usual one for the board
+fn configured_win_length(options: &CliOptions, board: &Board) -> usize {
+    options
+        .config
+        .win_length
+        .and_then(|win_length| cli::check_win_length(win_length, board).ok())
+        .unwrap_or_else(|| default_win_length(board))
+}
+
+fn exit_with_error(error: impl Display) -> ! {
+    eprintln!("{error}");
+    process::exit(1);
+}
+
+/// Asks the user how large the board should be. Empty answers pick the size from the config file, or else the
+/// standard 3x3 board.
+fn ask_board(config: &Config) -> Board {
+    loop {
+        let width = ask_number(
+            "How wide should the board be?",
+            BOARD_MIN_WIDTH..=BOARD_MAX_WIDTH,
+            config
+                .width
+                .or(config.height)
+                .unwrap_or(BOARD_STANDARD_WIDTH),
+        );
+        let height = ask_number(
+            "How tall should the board be?",
+            BOARD_MIN_WIDTH..=BOARD_MAX_WIDTH,
+            config.height.unwrap_or(width),
+        );
+        match Board::new(width, height) {
+            Ok(board) => return board,
+            Err(e) => println!("{e}"),
+        }
+    }
+}
+
+/// Asks the user how many pieces in a row it takes to win. The line has to fit on the board.
+fn ask_win_length(board: &Board, default: usize) -> usize {
+    let longest_line = cmp::max(board.width(), board.height());
+    ask_number(
+        "How many pieces in a row does it take to win?",
+        MIN_WIN_LENGTH..=longest_line,
+        default,
+    )
+}
+
+/// The whole short side of the board, but no more than Gomoku's five in a row
+fn default_win_length(board: &Board) -> usize {
+    cmp::min(
+        cmp::min(board.width(), board.height()),
+        MAX_DEFAULT_WIN_LENGTH,
+    )
+}
+
+/// Sets the players up the way the command line says, with defaults for anything left out
+fn cli_players(options: &CliOptions) -> PlayerList {
+    let symbols = default_symbols(options);
+    // Like pressing Enter at every question: a human against a minimax AI
+    let default_choices = [
+        PlayerChoice::Human,
+        PlayerChoice::AI(options.default_contender(1)),
+    ];
+    let seats = [(0, &options.player_1), (1, &options.player_2)].map(|(seat, choice)| {
+        let strategy = match choice.as_ref().unwrap_or(&default_choices[seat]) {
+            PlayerChoice::Human => None,
+            PlayerChoice::AI(contender) => Some(contender.strategy),
+            PlayerChoice::Remote => unreachable!("Remote players only come with --host"),
+        };
+        let symbol = symbols[seat];
+        (
+            default_name(options, seat, &symbol.to_string()),
+            symbol,
+            strategy,
+        )
+    });
+    let [first, second] = if options.first == Some(2) {
+        let [player_1, player_2] = seats;
+        [player_2, player_1]
+    } else {
+        seats
+    };
+    let player = |(name, symbol, strategy): (String, char, Option<AIStrategy>), piece| {
+        seated_player(&name, symbol, piece, strategy)
+    };
+    PlayerList::new(player(first, 1), player(second, 2))
+}
+
+/// Asks the user which kind of AI they want to play against, and how good it should be, unless the command line
+/// already said how good. Returns the AI for each seat, in seat order, since each seat may have its own think time.
+fn ask_ai_strategy(options: &CliOptions) -> [AIStrategy; 2] {
+    let use_mcts = loop {
+        println!(
+            "Which AI do you want to play against? Type 'minimax' or 'mcts' (Monte Carlo Tree Search), or press Enter for minimax."
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        match input.trim().to_lowercase().as_str() {
+            "" | "minimax" => break false,
+            "mcts" => break true,
+            val => println!("{val} is not a valid choice."),
+        }
+    };
+    let difficulty = options.difficulty.unwrap_or_else(|| {
+        ask_difficulty(options.config.difficulty.unwrap_or(Difficulty::Perfect))
+    });
+
+    [0, 1].map(|seat| {
+        if use_mcts {
+            AIStrategy::Mcts(difficulty.mcts_config(options.seed))
+        } else {
+            AIStrategy::Minimax(
+                difficulty.minimax_config(options.seed, options.seat_think_time(seat)),
+            )
+        }
+    })
+}
+
+fn ask_difficulty(default: Difficulty) -> Difficulty {
+    let default_name = match default {
+        Difficulty::Easy => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard => "hard",
+        Difficulty::Perfect => "perfect",
+    };
+    loop {
+        println!(
+            "How good should the AI be? Type 'easy', 'medium', 'hard' or 'perfect', or press Enter for {default_name}."
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        match input.trim().to_lowercase().as_str() {
+            "" => return default,
+            "easy" => return Difficulty::Easy,
+            "medium" => return Difficulty::Medium,
+            "hard" => return Difficulty::Hard,
+            "perfect" => return Difficulty::Perfect,
+            val => println!("{val} is not a valid choice."),
+        }
+    }
+}
+
+/// Asks the user whether they want to go first or second against the AI, play against a friend at the same keyboard, or just watch two AIs play each other
+fn ask_seating(options: &CliOptions) -> PlayerList {
+    loop {
+        println!(
+            "Do you want to go first? Type 'first' or 'second', 'friend' to play against someone next to you, or 'watch' to watch the AI play against itself. Press Enter to go first."
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        // The AI for each seat, if it has one. The other seats are for the user.
+        let (first_strategy, second_strategy) = match input.trim().to_lowercase().as_str() {
+            "" | "first" => (None, Some(ask_ai_strategy(options)[1])),
+            "second" => (Some(ask_ai_strategy(options)[0]), None),
+            "watch" => {
+                let [strategy_1, strategy_2] = ask_ai_strategy(options);
+                (Some(strategy_1), Some(strategy_2))
+            }
+            "friend" => return ask_hot_seat_players(options),
+            val => {
+                println!("{val} is not a valid choice.");
+                continue;
+            }
+        };
+        let [symbol_1, symbol_2] = default_symbols(options);
+        return PlayerList::new(
+            seated_player(
+                &default_name(options, 0, &symbol_1.to_string()),
+                symbol_1,
+                1,
+                first_strategy,
+            ),
+            seated_player(
+                &default_name(options, 1, &symbol_2.to_string()),
+                symbol_2,
+                2,
+                second_strategy,
+            ),
+        );
+    }
+}
+
+/// An AI player if there is a `strategy` for the seat, or else someone at this computer
+fn seated_player(name: &str, symbol: char, piece: u8, strategy: Option<AIStrategy>) -> Player {
+    match strategy {
+        Some(strategy) => Player::ai(name, symbol, PlayerPiece::new(piece), strategy),
+        None => Player::local(name, symbol, PlayerPiece::new(piece)),
+    }
+}
+
+/// Asks two people sharing the keyboard for their names and the symbols they want on the board
+fn ask_hot_seat_players(options: &CliOptions) -> PlayerList {
+    let [default_symbol_1, default_symbol_2] = default_symbols(options);
+    let first_name = ask_name(
+        "What is the name of the player who goes first?",
+        &default_name(options, 0, "Player 1"),
+    );
+    let first_symbol = ask_symbol(&first_name, default_symbol_1, None);
+    let second_name = ask_name(
+        "What is the name of the other player?",
+        &default_name(options, 1, "Player 2"),
+    );
+    let second_symbol = ask_symbol(
+        &second_name,
+        if default_symbol_2 == first_symbol {
+            symbol_other_than(first_symbol)
+        } else {
+            default_symbol_2
+        },
+        Some(first_symbol),
+    );
+    PlayerList::new(
+        Player::local(&first_name, first_symbol, PlayerPiece::new(1)),
+        Player::local(&second_name, second_symbol, PlayerPiece::new(2)),
+    )
+}
+
+/// The name for the player in `seat`, counting from 0, from the command line, or else the config file
+fn default_name(options: &CliOptions, seat: usize, fallback: &str) -> String {
+    [&options.name_1, &options.name_2][seat]
+        .clone()
+        .or_else(|| options.config.players[seat].name.clone())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+/// The symbols for the players, in seat order, from the command line, or else the config file, or else x and y.
+/// A symbol from the command line wins over the same one for the other player in the config file.
+fn default_symbols(options: &CliOptions) -> [char; 2] {
+    let other_than = |taken: Option<char>, preferred: char| {
+        if taken == Some(preferred) {
+            symbol_other_than(preferred)
+        } else {
+            preferred
+        }
+    };
+    let configured = |seat: usize| options.config.players[seat].symbol;
+    let symbol_1 = options
+        .symbol_1
+        .or(configured(0).filter(|&symbol| Some(symbol) != options.symbol_2))
+        .unwrap_or_else(|| other_than(options.symbol_2, 'x'));
+    let symbol_2 = options
+        .symbol_2
+        .or(configured(1).filter(|&symbol| symbol != symbol_1))
+        .unwrap_or_else(|| other_than(Some(symbol_1), 'y'));
+    [symbol_1, symbol_2]
+}
+
+fn ask_name(question: &str, default: &str) -> String {
+    println!("{question} Press Enter for {default}.");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read");
+    match input.trim() {
+        "" => default.to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Asks for the single character a player's pieces are drawn with. Both players can't use the same one.
+fn ask_symbol(name: &str, default: char, taken: Option<char>) -> char {
+    loop {
+        println!(
+            "Which symbol should {name} play with? Type a single character, or press Enter for {default}."
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        let mut characters = input.trim().chars();
+        match (characters.next(), characters.next()) {
+            (None, _) => return default,
+            (Some(symbol), None) if Some(symbol) != taken => return symbol,
+            (Some(symbol), None) => println!("{symbol} is already taken."),
+            _ => println!("{} is not a single character.", input.trim()),
+        }
+    }
+}
 
-    game.game_loop();
+fn ask_number(question: &str, allowed: RangeInclusive<usize>, default: usize) -> usize {
+    loop {
+        println!(
+            "{question} Type a number from {} to {}, or press Enter for {default}.",
+            allowed.start(),
+            allowed.end()
+        );
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read");
+        match input.trim() {
+            "" => return default,
+            val => match val.parse::<usize>() {
+                Ok(number) if allowed.contains(&number) => return number,
+                _ => println!("{val} is not a valid choice."),
+            },
+        }
+    }
 }
@@ -0,0 +1,51 @@
+use crate::network::NetworkError;
+use crate::network::protocol::Message;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+/// A TCP connection that sends and receives one `Message` per line
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Connection, NetworkError> {
+        let writer = stream.try_clone()?;
+        Ok(Connection {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), NetworkError> {
+        writeln!(self.writer, "{message}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Another handle on the same connection, for noticing when the other side hangs up while nobody is reading from it
+    pub fn hang_up_watcher(&self) -> Result<HangUpWatcher, NetworkError> {
+        Ok(HangUpWatcher(self.writer.try_clone()?))
+    }
+
+    /// Waits for the next message. A closed connection is reported as `NetworkError::Disconnected`.
+    pub fn receive(&mut self) -> Result<Message, NetworkError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(NetworkError::Disconnected);
+        }
+        line.trim_end_matches(['\r', '\n']).parse()
+    }
+}
+
+/// Watches a connection for the other side hanging up, without taking any messages from it
+pub struct HangUpWatcher(TcpStream);
+
+impl HangUpWatcher {
+    /// Waits until the other side hangs up or sends something, and returns whether they hung up
+    pub fn wait(&self) -> bool {
+        let mut byte = [0];
+        !matches!(self.0.peek(&mut byte), Ok(1..))
+    }
+}
@@ -0,0 +1,48 @@
+use crate::network::NetworkError;
+use crate::network::connection::Connection;
+use crate::network::protocol::Message;
+
+/// A game on a game server that is waiting for an opponent
+pub struct GameListing {
+    pub id: u32,
+    pub width: usize,
+    pub height: usize,
+    pub win_length: usize,
+    /// The name of the player waiting for an opponent
+    pub name: String,
+}
+
+/// A game on a game server that is being played, and can be watched
+pub struct LiveGame {
+    pub id: u32,
+    pub description: String,
+}
+
+/// Asks a game server which games are waiting for an opponent, and which are being played
+pub fn list_games(
+    connection: &mut Connection,
+) -> Result<(Vec<GameListing>, Vec<LiveGame>), NetworkError> {
+    connection.send(&Message::List)?;
+    let mut games = Vec::new();
+    let mut live_games = Vec::new();
+    loop {
+        match connection.receive()? {
+            Message::Game {
+                id,
+                width,
+                height,
+                win_length,
+                name,
+            } => games.push(GameListing {
+                id,
+                width,
+                height,
+                win_length,
+                name,
+            }),
+            Message::Live { id, description } => live_games.push(LiveGame { id, description }),
+            Message::End => return Ok((games, live_games)),
+            message => return Err(NetworkError::UnexpectedMessage(message)),
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! Playing against someone on another computer, over TCP.
+//!
+//! One player hosts the game and picks the board, and the other joins. The two then send each other one message per line,
+//! as plain text, so you can even play by hand with `nc localhost 7878`.
+//!
+//! ```text
+//! both:             HELLO 1                  The protocol version. Both sides send this straight away.
+//! joiner -> host:   JOIN o Ada               The symbol and name the joiner would like
+//! host -> joiner:   RULES 3 3 3              Board width, board height, and how many in a row it takes to win
+//! host -> joiner:   PLAYER 1 x Bob           Every player, in the order they move, with their final symbol
+//! host -> joiner:   PLAYER 2 o Ada
+//! host -> joiner:   START 2 5f3a...          Which seat the joiner got, and their session token. The game starts now.
+//! ```
+//!
+//! During the game both sides send the same messages:
+//!
+//! ```text
+//! MOVE 4          Placed a piece on cell 4, counting from the top left like `help` does
+//! RESTART         Wants to start over. The game restarts right away.
+//! EXIT            Left the game
+//! AGAIN yes|no    The answer to "Would you like to restart?" once the game is over
+//! ```
+//!
+//! Both sides keep their own copy of the game, and check every move they receive with `Board::is_valid_move`.
+//!
+//! If the connection drops in the middle of a game, the joiner connects to the host again, and sends `RESUME <token>`
+//! instead of `JOIN`. The host answers with the same `RULES`, `PLAYER` and `START` lines as before, followed by every move
+//! of the current game as `MOVES 4 0 8`, and the game goes on from there. The host's copy of the game is the one that counts.
+//! If the joiner does not come back within the grace period, they forfeit the game, or an AI takes over their seat.
+//!
+//! A game server (see the `server` module) speaks the same protocol. It takes the host's part towards both players.
+
+pub mod connection;
+pub mod lobby;
+pub mod protocol;
+pub mod reconnect;
+pub mod remote_player;
+pub mod spectator;
+
+use crate::board::{BOARD_MAX_WIDTH, BOARD_MIN_WIDTH, Board};
+use crate::gamestate::{GameReferee, GameState, MIN_WIN_LENGTH};
+use crate::network::connection::Connection;
+use crate::network::protocol::{Message, PROTOCOL_VERSION};
+use crate::network::reconnect::{ReconnectPolicy, Rejoin, Session};
+use crate::network::remote_player::RemotePlayer;
+use crate::player::base_player::{Player, PlayerPiece, symbol_other_than};
+use crate::player::playerlist::PlayerList;
+use crate::rng::Rng;
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    Disconnected,
+    InvalidMessage(String),
+    UnexpectedMessage(Message),
+    VersionMismatch(u32),
+    InvalidRules,
+    /// The server turned down what we asked for, and told us why
+    Refused(String),
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Io(e) => write!(f, "Network error: {e}"),
+            NetworkError::Disconnected => write!(f, "The other player disconnected."),
+            NetworkError::InvalidMessage(line) => write!(f, "Could not understand '{line}'."),
+            NetworkError::UnexpectedMessage(message) => {
+                write!(f, "Did not expect '{message}' at this point.")
+            }
+            NetworkError::VersionMismatch(version) => write!(
+                f,
+                "The other player runs version {version} of the protocol, but we need version {PROTOCOL_VERSION}."
+            ),
+            NetworkError::InvalidRules => write!(f, "The host picked a board we can't play on."),
+            NetworkError::Refused(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> NetworkError {
+        NetworkError::Io(e)
+    }
+}
+
+/// What the other side needs to know about a player before the game starts
+#[derive(Clone, Debug)]
+pub struct Seat {
+    pub name: String,
+    pub symbol: char,
+}
+
+/// Waits for another player to join on `port`, and sets up a game where the host goes first
+pub fn host(
+    port: u16,
+    board: Board,
+    win_length: usize,
+    name: &str,
+    symbol: char,
+    policy: ReconnectPolicy,
+) -> Result<GameState, NetworkError> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Waiting for someone to join on port {port}...");
+    host_on(listener, board, win_length, name, symbol, policy)
+}
+
+/// Waits for another player to join on `listener`, and sets up a game where the host goes first
+pub fn host_on(
+    listener: TcpListener,
+    board: Board,
+    win_length: usize,
+    name: &str,
+    symbol: char,
+    policy: ReconnectPolicy,
+) -> Result<GameState, NetworkError> {
+    let (stream, address) = listener.accept()?;
+    let mut connection = Connection::new(stream)?;
+
+    greet(&mut connection)?;
+    let guest = receive_join(&mut connection)?;
+    let seats = [
+        Seat {
+            name: name.to_string(),
+            symbol,
+        },
+        guest.with_symbol_besides(symbol),
+    ];
+    println!("{} joined from {address}.", seats[1].name);
+    let token = reconnect::new_token(&mut Rng::from_seed_or_time(None));
+    send_game_start(&mut connection, &board, win_length, &seats, 2, &token)?;
+
+    let [_, guest] = seats;
+    let session = Session {
+        token,
+        rejoin: Rejoin::Listener(listener),
+        policy,
+    };
+    let players = PlayerList::new(
+        Player::local(name, symbol, PlayerPiece::new(1)),
+        remote_player(&guest.name, guest.symbol, 2, connection, session),
+    );
+    Ok(GameState::new(board, GameReferee::new(win_length), players))
+}
+
+/// Joins a game hosted at `address`, e.g. `localhost:7878`
+pub fn join(
+    address: &str,
+    name: &str,
+    symbol: char,
+    policy: ReconnectPolicy,
+) -> Result<GameState, NetworkError> {
+    let mut connection = connect(address, name, symbol)?;
+    let (board, win_length) = wait_for_rules(&mut connection)?;
+    let rejoin = Rejoin::Dial(address.to_string());
+    take_seat(connection, board, win_length, name, rejoin, policy)
+}
+
+/// Connects to a host or a game server, and introduces ourselves
+pub fn connect(address: &str, name: &str, symbol: char) -> Result<Connection, NetworkError> {
+    let mut connection = Connection::new(TcpStream::connect(address)?)?;
+    greet(&mut connection)?;
+    connection.send(&Message::Join {
+        symbol,
+        name: name.to_string(),
+    })?;
+    Ok(connection)
+}
+
+/// Waits until the game starts, and returns the board to play on and how many in a row it takes to win
+pub fn wait_for_rules(connection: &mut Connection) -> Result<(Board, usize), NetworkError> {
+    loop {
+        match connection.receive()? {
+            Message::Rules {
+                width,
+                height,
+                win_length,
+            } => return check_rules(width, height, win_length).ok_or(NetworkError::InvalidRules),
+            // A game server tells us when it has put us in a game, but nobody has joined it yet
+            Message::Created(id) => println!("Waiting for an opponent in game {id}..."),
+            Message::Error(reason) => return Err(NetworkError::Refused(reason)),
+            message => return Err(NetworkError::UnexpectedMessage(message)),
+        }
+    }
+}
+
+/// Finds out who we are playing against, and which seat we got, once the rules are known.
+///
+/// `rejoin` is how we get back into the game if the connection drops.
+pub fn take_seat(
+    mut connection: Connection,
+    board: Board,
+    win_length: usize,
+    name: &str,
+    rejoin: Rejoin,
+    policy: ReconnectPolicy,
+) -> Result<GameState, NetworkError> {
+    let ([(first_seat, first), (second_seat, second)], our_seat, token) =
+        match receive_players(&mut connection)? {
+            (seats, Message::Start { seat, session }) => (seats, seat, session),
+            (_, message) => return Err(NetworkError::UnexpectedMessage(message)),
+        };
+    let session = Session {
+        token,
+        rejoin,
+        policy,
+    };
+    if our_seat != first_seat && our_seat != second_seat {
+        return Err(NetworkError::InvalidRules);
+    }
+
+    let players = if our_seat == first_seat {
+        PlayerList::new(
+            Player::local(name, first.symbol, PlayerPiece::new(first_seat)),
+            remote_player(
+                &second.name,
+                second.symbol,
+                second_seat,
+                connection,
+                session,
+            ),
+        )
+    } else {
+        PlayerList::new(
+            remote_player(&first.name, first.symbol, first_seat, connection, session),
+            Player::local(name, second.symbol, PlayerPiece::new(second_seat)),
+        )
+    };
+    Ok(GameState::new(board, GameReferee::new(win_length), players))
+}
+
+/// Reads the name and symbol a player who just connected would like to play with
+pub fn receive_join(connection: &mut Connection) -> Result<Seat, NetworkError> {
+    match connection.receive()? {
+        Message::Join { symbol, name } => Ok(Seat { name, symbol }),
+        message => Err(NetworkError::UnexpectedMessage(message)),
+    }
+}
+
+/// Reads the `PLAYER` lines for both seats, in seat order, along with the message that came after them
+pub fn receive_players(
+    connection: &mut Connection,
+) -> Result<([(u8, Seat); 2], Message), NetworkError> {
+    let mut seats = Vec::new();
+    let next_message = loop {
+        match connection.receive()? {
+            Message::Player { seat, symbol, name } => seats.push((seat, Seat { name, symbol })),
+            message => break message,
+        }
+    };
+    seats.sort_by_key(|(seat, _)| *seat);
+    let seats = <[_; 2]>::try_from(seats).map_err(|_| NetworkError::InvalidRules)?;
+    if seats[0].0 == seats[1].0 {
+        return Err(NetworkError::InvalidRules);
+    }
+    Ok((seats, next_message))
+}
+
+/// Tells a player the rules and who is playing, which of the `seats` is theirs, and the token they need to get back in.
+/// The game starts right after this.
+pub fn send_game_start(
+    connection: &mut Connection,
+    board: &Board,
+    win_length: usize,
+    seats: &[Seat],
+    their_seat: u8,
+    token: &str,
+) -> Result<(), NetworkError> {
+    send_rules_and_players(connection, board, win_length, seats)?;
+    connection.send(&Message::Start {
+        seat: their_seat,
+        session: token.to_string(),
+    })
+}
+
+/// Tells the other side which board we play on, and who plays
+pub fn send_rules_and_players(
+    connection: &mut Connection,
+    board: &Board,
+    win_length: usize,
+    seats: &[Seat],
+) -> Result<(), NetworkError> {
+    connection.send(&Message::Rules {
+        width: board.width(),
+        height: board.height(),
+        win_length,
+    })?;
+    for (seat, player) in (1..).zip(seats) {
+        connection.send(&Message::Player {
+            seat,
+            symbol: player.symbol,
+            name: player.name.clone(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Creates an empty board for the given rules, if it's one we are willing to play on.
+///
+/// Whoever picks the board could be on the other end of the network, so we don't want to take their word for it.
+pub fn check_rules(width: usize, height: usize, win_length: usize) -> Option<(Board, usize)> {
+    let sides = BOARD_MIN_WIDTH..=BOARD_MAX_WIDTH;
+    let win_lengths = MIN_WIN_LENGTH..=cmp::max(width, height);
+    if sides.contains(&width) && sides.contains(&height) && win_lengths.contains(&win_length) {
+        Some((Board::new(width, height).ok()?, win_length))
+    } else {
+        None
+    }
+}
+
+impl Seat {
+    /// The player who got there first keeps their symbol if both picked the same one
+    pub fn with_symbol_besides(self, taken: char) -> Seat {
+        if self.symbol == taken {
+            Seat {
+                symbol: symbol_other_than(taken),
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/// Both sides start by telling each other which version of the protocol they speak
+pub fn greet(connection: &mut Connection) -> Result<(), NetworkError> {
+    connection.send(&Message::Hello(PROTOCOL_VERSION))?;
+    match connection.receive()? {
+        Message::Hello(PROTOCOL_VERSION) => Ok(()),
+        Message::Hello(version) => Err(NetworkError::VersionMismatch(version)),
+        message => Err(NetworkError::UnexpectedMessage(message)),
+    }
+}
+
+pub fn remote_player(
+    name: &str,
+    symbol: char,
+    seat: u8,
+    connection: Connection,
+    session: Session,
+) -> Player {
+    Player::remote(
+        name,
+        symbol,
+        PlayerPiece::new(seat),
+        Box::new(RemotePlayer::new(connection, session)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::InputType;
+    use std::thread;
+
+    /// The names and symbols of the players, in the order they move
+    fn seats(game: &GameState) -> Vec<(String, char)> {
+        game.players()
+            .iter()
+            .map(|player| (player.name.clone(), player.symbol))
+            .collect()
+    }
+
+    /// Waits for the remote player whose turn it is to move
+    fn remote_move(game: &GameState) -> usize {
+        match game.current_player().controller.handle_input(game) {
+            Ok(InputType::Coord(index)) => index,
+            _ => panic!("Expected a move from {}", game.current_player().name),
+        }
+    }
+
+    #[test]
+    fn host_and_join_over_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let host = thread::spawn(move || {
+            let board = Board::new(4, 3).unwrap();
+            let policy = ReconnectPolicy::default();
+            let mut game = host_on(listener, board, 3, "Ann", 'x', policy).unwrap();
+            game.place_piece(5).unwrap();
+            let reply = remote_move(&game);
+            (seats(&game), reply)
+        });
+
+        // The host was there first, and keeps x, so the joiner gets y
+        let mut game = join(&address, "Bob", 'x', ReconnectPolicy::default()).unwrap();
+        let expected_seats = vec![("Ann".to_string(), 'x'), ("Bob".to_string(), 'y')];
+        assert_eq!(seats(&game), expected_seats);
+        assert_eq!((game.board().width(), game.board().height()), (4, 3));
+        assert_eq!(game.referee().win_length(), 3);
+        assert!(game.current_player().is_remote());
+
+        let index = remote_move(&game);
+        assert_eq!(index, 5);
+        game.place_piece(index).unwrap();
+        game.place_piece(0).unwrap();
+        assert_eq!(host.join().unwrap(), (expected_seats, 0));
+    }
+
+    #[test]
+    fn refuses_another_version_of_the_protocol() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || {
+            let board = Board::new(3, 3).unwrap();
+            host_on(listener, board, 3, "Ann", 'x', ReconnectPolicy::default()).err()
+        });
+        let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        connection
+            .send(&Message::Hello(PROTOCOL_VERSION + 1))
+            .unwrap();
+        assert!(matches!(
+            host.join().unwrap(),
+            Some(NetworkError::VersionMismatch(version)) if version == PROTOCOL_VERSION + 1
+        ));
+    }
+}
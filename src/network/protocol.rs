@@ -0,0 +1,235 @@
+use crate::network::NetworkError;
+use std::fmt;
+use std::str::FromStr;
+
+/// Bumped whenever the messages change in a way older versions of the game would not understand
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// A single line sent between two players. See the `network` module for how they fit together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Hello(u32),
+    Join {
+        symbol: char,
+        name: String,
+    },
+    Rules {
+        width: usize,
+        height: usize,
+        win_length: usize,
+    },
+    Player {
+        seat: u8,
+        symbol: char,
+        name: String,
+    },
+    Start {
+        seat: u8,
+        /// The token that lets the player back into the game if their connection drops
+        session: String,
+    },
+    Resume(String),
+    Move(usize),
+    Restart,
+    Exit,
+    Again(bool),
+    // The lobby on a game server
+    List,
+    Game {
+        id: u32,
+        width: usize,
+        height: usize,
+        win_length: usize,
+        name: String,
+    },
+    End,
+    Create {
+        width: usize,
+        height: usize,
+        win_length: usize,
+    },
+    Created(u32),
+    Enter(u32),
+    Quick,
+    Error(String),
+    // Spectators
+    Watch(u32),
+    Live {
+        id: u32,
+        description: String,
+    },
+    Moves(Vec<usize>),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "HELLO {version}"),
+            Message::Join { symbol, name } => write!(f, "JOIN {symbol} {name}"),
+            Message::Rules {
+                width,
+                height,
+                win_length,
+            } => write!(f, "RULES {width} {height} {win_length}"),
+            Message::Player { seat, symbol, name } => write!(f, "PLAYER {seat} {symbol} {name}"),
+            Message::Start { seat, session } => write!(f, "START {seat} {session}"),
+            Message::Resume(session) => write!(f, "RESUME {session}"),
+            Message::Move(index) => write!(f, "MOVE {index}"),
+            Message::Restart => write!(f, "RESTART"),
+            Message::Exit => write!(f, "EXIT"),
+            Message::Again(true) => write!(f, "AGAIN yes"),
+            Message::Again(false) => write!(f, "AGAIN no"),
+            Message::List => write!(f, "LIST"),
+            Message::Game {
+                id,
+                width,
+                height,
+                win_length,
+                name,
+            } => write!(f, "GAME {id} {width} {height} {win_length} {name}"),
+            Message::End => write!(f, "END"),
+            Message::Create {
+                width,
+                height,
+                win_length,
+            } => write!(f, "CREATE {width} {height} {win_length}"),
+            Message::Created(id) => write!(f, "CREATED {id}"),
+            Message::Enter(id) => write!(f, "ENTER {id}"),
+            Message::Quick => write!(f, "QUICK"),
+            Message::Error(reason) => write!(f, "ERROR {reason}"),
+            Message::Watch(id) => write!(f, "WATCH {id}"),
+            Message::Live { id, description } => write!(f, "LIVE {id} {description}"),
+            Message::Moves(moves) => {
+                write!(f, "MOVES")?;
+                for index in moves {
+                    write!(f, " {index}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Message {
+    type Err = NetworkError;
+
+    fn from_str(line: &str) -> Result<Message, NetworkError> {
+        let invalid = || NetworkError::InvalidMessage(line.to_string());
+        let (keyword, arguments) = line.split_once(' ').unwrap_or((line, ""));
+        // Names may contain spaces, so they always come last, and take up the rest of the line
+        let mut fields = arguments.splitn(2, ' ');
+        let mut next_field = || fields.next().ok_or_else(invalid);
+        let message = match keyword {
+            "HELLO" => Message::Hello(next_field()?.parse().map_err(|_| invalid())?),
+            "JOIN" => Message::Join {
+                symbol: parse_symbol(next_field()?).ok_or_else(invalid)?,
+                name: next_field()?.to_string(),
+            },
+            "RULES" => {
+                let [width, height, win_length] = parse_numbers(arguments).ok_or_else(invalid)?;
+                Message::Rules {
+                    width,
+                    height,
+                    win_length,
+                }
+            }
+            "CREATE" => {
+                let [width, height, win_length] = parse_numbers(arguments).ok_or_else(invalid)?;
+                Message::Create {
+                    width,
+                    height,
+                    win_length,
+                }
+            }
+            "GAME" => {
+                let fields: Vec<&str> = arguments.splitn(5, ' ').collect();
+                let [id, width, height, win_length, name] = fields[..] else {
+                    return Err(invalid());
+                };
+                let (Ok(id), Ok(width), Ok(height), Ok(win_length)) = (
+                    id.parse(),
+                    width.parse(),
+                    height.parse(),
+                    win_length.parse(),
+                ) else {
+                    return Err(invalid());
+                };
+                Message::Game {
+                    id,
+                    width,
+                    height,
+                    win_length,
+                    name: name.to_string(),
+                }
+            }
+            "PLAYER" => {
+                let (seat, rest) = arguments.split_once(' ').ok_or_else(invalid)?;
+                let (symbol, name) = rest.split_once(' ').ok_or_else(invalid)?;
+                Message::Player {
+                    seat: seat.parse().map_err(|_| invalid())?,
+                    symbol: parse_symbol(symbol).ok_or_else(invalid)?,
+                    name: name.to_string(),
+                }
+            }
+            "START" => {
+                let (seat, session) = arguments.split_once(' ').ok_or_else(invalid)?;
+                Message::Start {
+                    seat: seat.parse().map_err(|_| invalid())?,
+                    session: parse_session(session).ok_or_else(invalid)?,
+                }
+            }
+            "RESUME" => Message::Resume(parse_session(arguments).ok_or_else(invalid)?),
+            "MOVE" => Message::Move(arguments.parse().map_err(|_| invalid())?),
+            "RESTART" if arguments.is_empty() => Message::Restart,
+            "EXIT" if arguments.is_empty() => Message::Exit,
+            "AGAIN" if arguments == "yes" => Message::Again(true),
+            "AGAIN" if arguments == "no" => Message::Again(false),
+            "LIST" if arguments.is_empty() => Message::List,
+            "END" if arguments.is_empty() => Message::End,
+            "CREATED" => Message::Created(arguments.parse().map_err(|_| invalid())?),
+            "ENTER" => Message::Enter(arguments.parse().map_err(|_| invalid())?),
+            "QUICK" if arguments.is_empty() => Message::Quick,
+            "ERROR" => Message::Error(arguments.to_string()),
+            "WATCH" => Message::Watch(arguments.parse().map_err(|_| invalid())?),
+            "LIVE" => {
+                let (id, description) = arguments.split_once(' ').ok_or_else(invalid)?;
+                Message::Live {
+                    id: id.parse().map_err(|_| invalid())?,
+                    description: description.to_string(),
+                }
+            }
+            "MOVES" if arguments.is_empty() => Message::Moves(Vec::new()),
+            "MOVES" => Message::Moves(
+                arguments
+                    .split(' ')
+                    .map(|index| index.parse().map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(invalid()),
+        };
+        Ok(message)
+    }
+}
+
+/// Parses exactly `N` numbers separated by spaces
+fn parse_numbers<const N: usize>(arguments: &str) -> Option<[usize; N]> {
+    let numbers: Vec<usize> = arguments
+        .split(' ')
+        .map(|number| number.parse().ok())
+        .collect::<Option<_>>()?;
+    numbers.try_into().ok()
+}
+
+/// Session tokens are a single word, so they can't be mistaken for anything else on the line
+fn parse_session(field: &str) -> Option<String> {
+    (!field.is_empty() && !field.contains(char::is_whitespace)).then(|| field.to_string())
+}
+
+/// Symbols are a single character that is not a space, or we couldn't tell where they end
+fn parse_symbol(field: &str) -> Option<char> {
+    let mut characters = field.chars();
+    match (characters.next(), characters.next()) {
+        (Some(symbol), None) if !symbol.is_whitespace() => Some(symbol),
+        _ => None,
+    }
+}
@@ -0,0 +1,189 @@
+use crate::GameState;
+use crate::ai::AIStrategy;
+use crate::network::connection::Connection;
+use crate::network::protocol::Message;
+use crate::network::{self, NetworkError, Seat};
+use crate::rng::Rng;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often we check for a player knocking on our listener while we wait for them to come back
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// How long we wait before trying to connect to the host again
+const DIAL_INTERVAL: Duration = Duration::from_secs(1);
+/// Someone who connects to us has this long to say which game they are coming back to
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What happens when the connection to a remote player drops in the middle of a game
+#[derive(Clone, Copy)]
+pub struct ReconnectPolicy {
+    /// How long they have to come back
+    pub grace_period: Duration,
+    /// The AI that takes over their seat if they don't come back in time. Without one, they forfeit the game.
+    pub stand_in: Option<AIStrategy>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            grace_period: Duration::from_secs(60),
+            stand_in: None,
+        }
+    }
+}
+
+/// How a remote player gets back into the game after their connection drops
+pub enum Rejoin {
+    /// We are hosting the game, so they connect to our listener again
+    Listener(TcpListener),
+    /// They play on a game server, whose lobby hands us their new connection
+    Lobby(Receiver<Connection>),
+    /// They are hosting the game at this address, so it's up to us to connect to them again
+    Dial(String),
+}
+
+/// Everything needed to pick up a game with a remote player after their connection drops
+pub struct Session {
+    /// The token the player who joined got in `START`, and sends back in `RESUME` to prove who they are
+    pub token: String,
+    pub rejoin: Rejoin,
+    pub policy: ReconnectPolicy,
+}
+
+/// Creates a token that is hard enough to guess that nobody else can take over a player's seat
+pub fn new_token(rng: &mut Rng) -> String {
+    format!("{:016x}{:016x}", rng.next_u64(), rng.next_u64())
+}
+
+impl Session {
+    /// Waits for the remote player to come back, for as long as the grace period allows. This is only called on their turn.
+    ///
+    /// If we are the ones who keep track of the game, we catch them up on it. Otherwise we get every move of the current game,
+    /// which we need to catch up on ourselves.
+    pub fn reconnect(&self, game_state: &GameState) -> Option<(Connection, Option<Vec<usize>>)> {
+        let deadline = Instant::now() + self.policy.grace_period;
+        match &self.rejoin {
+            Rejoin::Listener(listener) => {
+                let mut connection = self.accept(listener, deadline)?;
+                send_resync(&mut connection, game_state, &self.token).ok()?;
+                Some((connection, None))
+            }
+            Rejoin::Lobby(receiver) => loop {
+                // The lobby checked the token already, but the player may have given up on an earlier connection
+                let mut connection = match receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                {
+                    Ok(connection) => connection,
+                    Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+                };
+                if send_resync(&mut connection, game_state, &self.token).is_ok() {
+                    return Some((connection, None));
+                }
+            },
+            Rejoin::Dial(address) => {
+                let (connection, moves) = self.dial(address, deadline)?;
+                Some((connection, Some(moves)))
+            }
+        }
+    }
+
+    /// Accepts connections until the player with our token shows up, turning away anyone else
+    fn accept(&self, listener: &TcpListener, deadline: Instant) -> Option<Connection> {
+        listener.set_nonblocking(true).ok()?;
+        let connection = loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Ok(Some(connection)) = self.check_token(stream) {
+                        break Some(connection);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    thread::sleep(ACCEPT_INTERVAL);
+                }
+                Err(_) => break None,
+            }
+        };
+        let _ = listener.set_nonblocking(false);
+        connection
+    }
+
+    fn check_token(&self, stream: TcpStream) -> Result<Option<Connection>, NetworkError> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut connection = Connection::new(stream.try_clone()?)?;
+        network::greet(&mut connection)?;
+        match connection.receive()? {
+            Message::Resume(token) if token == self.token => {
+                stream.set_read_timeout(None)?;
+                Ok(Some(connection))
+            }
+            Message::Join { .. } => {
+                connection.send(&Message::Error("The game has already started.".to_string()))?;
+                Ok(None)
+            }
+            _ => {
+                connection.send(&Message::Error("That is not your game.".to_string()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Keeps connecting to the host until it lets us back in, or says no
+    fn dial(&self, address: &str, deadline: Instant) -> Option<(Connection, Vec<usize>)> {
+        loop {
+            match self.resume(address) {
+                Ok(resumed) => return Some(resumed),
+                Err(NetworkError::Refused(reason)) => {
+                    println!("{reason}");
+                    return None;
+                }
+                Err(_) if Instant::now() + DIAL_INTERVAL < deadline => thread::sleep(DIAL_INTERVAL),
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn resume(&self, address: &str) -> Result<(Connection, Vec<usize>), NetworkError> {
+        let mut connection = Connection::new(TcpStream::connect(address)?)?;
+        network::greet(&mut connection)?;
+        connection.send(&Message::Resume(self.token.clone()))?;
+        // We already know the rules and the players, so all we need are the moves
+        network::wait_for_rules(&mut connection)?;
+        match network::receive_players(&mut connection)? {
+            (_, Message::Start { .. }) => {}
+            (_, message) => return Err(NetworkError::UnexpectedMessage(message)),
+        }
+        match connection.receive()? {
+            Message::Moves(moves) => Ok((connection, moves)),
+            message => Err(NetworkError::UnexpectedMessage(message)),
+        }
+    }
+}
+
+/// Tells the player whose turn it is, who just came back, which game they are in, and every move made in it so far
+fn send_resync(
+    connection: &mut Connection,
+    game_state: &GameState,
+    token: &str,
+) -> Result<(), NetworkError> {
+    let seats: Vec<Seat> = game_state
+        .players()
+        .iter()
+        .map(|player| Seat {
+            name: player.name.clone(),
+            symbol: player.symbol,
+        })
+        .collect();
+    network::send_game_start(
+        connection,
+        game_state.board(),
+        game_state.referee().win_length(),
+        &seats,
+        *game_state.piece_to_move(),
+        token,
+    )?;
+    connection.send(&Message::Moves(game_state.moves()))
+}
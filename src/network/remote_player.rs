@@ -0,0 +1,290 @@
+use crate::GameState;
+use crate::controller::*;
+use crate::network::NetworkError;
+use crate::network::connection::Connection;
+use crate::network::protocol::Message;
+use crate::network::reconnect::Session;
+use crate::player::base_player::ai_controller;
+use std::cell::RefCell;
+
+/// A player sitting at another computer, whose moves arrive over the network
+pub struct RemotePlayer {
+    // `PlayerController` only hands us `&self`, but sending and receiving needs a mutable connection
+    link: RefCell<Link>,
+    session: Session,
+}
+
+/// Who is behind the seat of a remote player
+enum Link {
+    Connected(Connection),
+    /// They did not come back in time after their connection dropped, so an AI plays for them
+    StandIn(Box<dyn PlayerController>),
+    /// They did not come back in time after their connection dropped, and lost the game
+    Forfeited,
+}
+
+impl RemotePlayer {
+    pub fn new(connection: Connection, session: Session) -> RemotePlayer {
+        RemotePlayer {
+            link: RefCell::new(Link::Connected(connection)),
+            session,
+        }
+    }
+
+    /// Gives the other player the grace period to come back, and decides who plays their seat if they don't.
+    /// Returns the moves to catch up on if the other side keeps track of the game.
+    fn reconnect(&self, game_state: &GameState) -> Option<Vec<usize>> {
+        let name = &game_state.current_player().name;
+        println!(
+            "Lost the connection to {name}. Waiting up to {} seconds for them to come back...",
+            self.session.policy.grace_period.as_secs()
+        );
+        let (link, moves) = match self.session.reconnect(game_state) {
+            Some((connection, moves)) => {
+                println!("{name} is back.");
+                (Link::Connected(connection), moves)
+            }
+            None => match self.session.policy.stand_in {
+                Some(strategy) => {
+                    println!(
+                        "{name} did not come back in time, so the AI plays for them from now on."
+                    );
+                    (Link::StandIn(ai_controller(strategy)), None)
+                }
+                None => {
+                    println!("{name} did not come back in time, and forfeits the game.");
+                    (Link::Forfeited, None)
+                }
+            },
+        };
+        *self.link.borrow_mut() = link;
+        moves
+    }
+}
+
+impl PlayerController for RemotePlayer {
+    /// Waits for the other player's move. We can't ask them to try again if something is wrong, so then we end the game.
+    fn handle_input(&self, game_state: &GameState) -> Result<InputType, InputError> {
+        loop {
+            let received = match &mut *self.link.borrow_mut() {
+                Link::Connected(connection) => connection.receive(),
+                Link::StandIn(stand_in) => return stand_in.handle_input(game_state),
+                Link::Forfeited => return Ok(InputType::Forfeit),
+            };
+            match received {
+                // The other side validates moves too, but we don't want to rely on that
+                Ok(Message::Move(index)) => {
+                    return match game_state.board().is_valid_move(index) {
+                        Ok(()) => Ok(InputType::Coord(index)),
+                        Err(e) => {
+                            println!("The other player made an illegal move: {e}");
+                            Ok(InputType::Exit)
+                        }
+                    };
+                }
+                Ok(Message::Restart) => return Ok(InputType::Restart),
+                Ok(Message::Exit) => {
+                    println!("The other player left the game.");
+                    return Ok(InputType::Exit);
+                }
+                Ok(message) => {
+                    println!("The other player sent something unexpected: {message}");
+                    return Ok(InputType::Exit);
+                }
+                // Unless we have to catch up, we either wait for their move again, or someone else plays their seat now
+                Err(NetworkError::Disconnected | NetworkError::Io(_)) => {
+                    if let Some(moves) = self.reconnect(game_state) {
+                        return Ok(InputType::Resync(moves));
+                    }
+                }
+                Err(e) => {
+                    println!("{e}");
+                    return Ok(InputType::Exit);
+                }
+            }
+        }
+    }
+
+    fn get_yes_no(&self) -> Result<bool, InputError> {
+        match &mut *self.link.borrow_mut() {
+            // If the other player is gone, they certainly don't want to play again
+            Link::Connected(connection) => match connection.receive() {
+                Ok(Message::Again(choice)) => Ok(choice),
+                _ => Ok(false),
+            },
+            Link::StandIn(stand_in) => stand_in.get_yes_no(),
+            Link::Forfeited => Ok(false),
+        }
+    }
+
+    fn describe_last_move(&self) -> Option<String> {
+        match &*self.link.borrow() {
+            Link::StandIn(stand_in) => stand_in.describe_last_move(),
+            _ => None,
+        }
+    }
+
+    fn observe(&self, event: GameEvent) {
+        let message = match event {
+            GameEvent::Moved(index) => Message::Move(index),
+            GameEvent::Restarted => Message::Restart,
+            GameEvent::Exited => Message::Exit,
+            GameEvent::Answered(choice) => Message::Again(choice),
+        };
+        // If this fails the connection is gone, which we notice the next time we wait for the other player
+        if let Link::Connected(connection) = &mut *self.link.borrow_mut() {
+            let _ = connection.send(&message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AIStrategy;
+    use crate::ai::minimax::MinimaxConfig;
+    use crate::board::Board;
+    use crate::gamestate::GameReferee;
+    use crate::network::reconnect::{ReconnectPolicy, Rejoin};
+    use crate::network::{self, remote_player};
+    use crate::player::base_player::{Player, PlayerPiece};
+    use crate::player::playerlist::PlayerList;
+    use crate::render::AsciiRenderer;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn minimax() -> AIStrategy {
+        AIStrategy::Minimax(MinimaxConfig::default())
+    }
+
+    /// A 3x3 game between a minimax AI and a player who connected from localhost, as if we were hosting it.
+    /// Returns the game, where the player can reconnect, and our end of their first connection.
+    fn game_with_remote_player(policy: ReconnectPolicy) -> (GameState, String, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let their_end = TcpStream::connect(&address).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let session = Session {
+            token: TOKEN.to_string(),
+            rejoin: Rejoin::Listener(listener),
+            policy,
+        };
+        let players = PlayerList::new(
+            Player::ai("Ann", 'x', PlayerPiece::new(1), minimax()),
+            remote_player("Bob", 'y', 2, Connection::new(stream).unwrap(), session),
+        );
+        let game = GameState::new(Board::new(3, 3).unwrap(), GameReferee::new(3), players);
+        (game, address, their_end)
+    }
+
+    /// Connects to the game again with `token`, and returns the connection with the greetings done
+    fn resume(address: &str, token: &str) -> Connection {
+        let mut connection = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        network::greet(&mut connection).unwrap();
+        connection
+            .send(&Message::Resume(token.to_string()))
+            .unwrap();
+        connection
+    }
+
+    /// Takes the remote player's turn, which starts with noticing that their connection dropped
+    fn remote_turn(game: &GameState) -> InputType {
+        match game.current_player().controller.handle_input(game) {
+            Ok(input) => input,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    fn policy(grace_period: Duration, stand_in: Option<AIStrategy>) -> ReconnectPolicy {
+        ReconnectPolicy {
+            grace_period,
+            stand_in,
+        }
+    }
+
+    #[test]
+    fn a_player_who_comes_back_in_time_is_caught_up() {
+        let (mut game, address, their_end) =
+            game_with_remote_player(policy(Duration::from_secs(10), None));
+        game.place_piece(4).unwrap();
+        drop(their_end);
+
+        let player = thread::spawn(move || {
+            let mut connection = resume(&address, TOKEN);
+            let (board, win_length) = network::wait_for_rules(&mut connection).unwrap();
+            let (seats, start) = network::receive_players(&mut connection).unwrap();
+            let moves = connection.receive().unwrap();
+            connection.send(&Message::Move(0)).unwrap();
+            let names = seats.map(|(_, seat)| seat.name);
+            (
+                (board.width(), board.height(), win_length),
+                names,
+                start,
+                moves,
+            )
+        });
+
+        assert!(matches!(remote_turn(&game), InputType::Coord(0)));
+        let (rules, names, start, moves) = player.join().unwrap();
+        assert_eq!(rules, (3, 3, 3));
+        assert_eq!(names, ["Ann", "Bob"]);
+        assert!(matches!(start, Message::Start { seat: 2, session } if session == TOKEN));
+        assert!(matches!(moves, Message::Moves(moves) if moves == [4]));
+    }
+
+    #[test]
+    fn a_wrong_token_is_turned_away() {
+        let (mut game, address, their_end) =
+            game_with_remote_player(policy(Duration::from_secs(10), None));
+        game.place_piece(4).unwrap();
+        drop(their_end);
+
+        let player = thread::spawn(move || {
+            let mut impostor = resume(&address, "fedcba9876543210");
+            let answer = impostor.receive().unwrap();
+
+            let mut connection = resume(&address, TOKEN);
+            network::wait_for_rules(&mut connection).unwrap();
+            network::receive_players(&mut connection).unwrap();
+            connection.receive().unwrap();
+            connection.send(&Message::Move(8)).unwrap();
+            answer
+        });
+
+        assert!(matches!(remote_turn(&game), InputType::Coord(8)));
+        assert!(matches!(
+            player.join().unwrap(),
+            Message::Error(reason) if reason == "That is not your game."
+        ));
+    }
+
+    #[test]
+    fn a_player_who_does_not_come_back_forfeits() {
+        let (mut game, _, their_end) =
+            game_with_remote_player(policy(Duration::from_millis(200), None));
+        drop(their_end);
+        game.game_loop(&AsciiRenderer);
+        // The AI moved first, and won when the other player did not come back for their turn
+        assert_eq!(game.moves().len(), 1);
+        assert_eq!(game.score().wins, [1, 0]);
+    }
+
+    #[test]
+    fn a_stand_in_takes_over_from_a_player_who_does_not_come_back() {
+        let (mut game, _, their_end) =
+            game_with_remote_player(policy(Duration::from_millis(200), Some(minimax())));
+        game.place_piece(4).unwrap();
+        drop(their_end);
+
+        let InputType::Coord(index) = remote_turn(&game) else {
+            panic!("Expected the stand-in to move");
+        };
+        game.place_piece(index).unwrap();
+        // Only an AI has anything to say about how it picked its move
+        let stand_in = game.players().iter().nth(1).unwrap();
+        assert!(stand_in.controller.describe_last_move().is_some());
+    }
+}
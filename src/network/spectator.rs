@@ -0,0 +1,94 @@
+use crate::GameState;
+use crate::board::Board;
+use crate::controller::*;
+use crate::gamestate::{GameOutcome, GameReferee};
+use crate::network::connection::Connection;
+use crate::network::protocol::Message;
+use crate::network::{self, NetworkError};
+use crate::player::base_player::{Player, PlayerPiece};
+use crate::player::playerlist::PlayerList;
+use crate::render::{self, Renderer};
+
+/// A player in a game we are only watching. The server tells us about their moves, so we never have to ask them anything.
+struct WatchedPlayer {}
+
+impl PlayerController for WatchedPlayer {
+    fn handle_input(&self, _gamestate: &GameState) -> Result<InputType, InputError> {
+        Ok(InputType::Exit)
+    }
+
+    fn get_yes_no(&self) -> Result<bool, InputError> {
+        Ok(false)
+    }
+}
+
+/// Watches a game on a game server, drawing the board with `renderer` after every move, until the game ends.
+///
+/// This picks up after the server has accepted our `WATCH` and sent the rules.
+pub fn watch(
+    mut connection: Connection,
+    board: Board,
+    win_length: usize,
+    renderer: &dyn Renderer,
+) -> Result<(), NetworkError> {
+    let (seats, moves) = match network::receive_players(&mut connection)? {
+        (seats, Message::Moves(moves)) => (seats, moves),
+        (_, message) => return Err(NetworkError::UnexpectedMessage(message)),
+    };
+    let [first, second] = seats.map(|(seat, player)| {
+        Player::remote(
+            &player.name,
+            player.symbol,
+            PlayerPiece::new(seat),
+            Box::new(WatchedPlayer {}),
+        )
+    });
+    let mut game = GameState::new(
+        board,
+        GameReferee::new(win_length),
+        PlayerList::new(first, second),
+    );
+
+    // Catching up on the moves made before we started watching
+    for index in moves {
+        place(&mut game, index)?;
+    }
+    render::print_board(renderer, game.board(), game.players());
+    loop {
+        match connection.receive() {
+            Ok(Message::Move(index)) => {
+                place(&mut game, index)?;
+                render::print_board(renderer, game.board(), game.players());
+                announce_outcome(&game);
+            }
+            Ok(Message::Restart) => {
+                println!("The players started a new game.");
+                game.restart();
+                render::print_board(renderer, game.board(), game.players());
+            }
+            Ok(Message::Exit) | Err(NetworkError::Disconnected) => {
+                println!("The game is over.");
+                return Ok(());
+            }
+            Ok(message) => return Err(NetworkError::UnexpectedMessage(message)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn place(game: &mut GameState, index: usize) -> Result<(), NetworkError> {
+    game.place_piece(index)
+        .map_err(|_| NetworkError::InvalidMessage(Message::Move(index).to_string()))
+}
+
+fn announce_outcome(game: &GameState) {
+    match game.outcome() {
+        Some(GameOutcome::Winner(piece)) => {
+            if let Some(winner) = game.players().iter().find(|p| p.player_piece == piece) {
+                println!("{} is the winner!", winner.name);
+            }
+        }
+        Some(GameOutcome::Draw) => println!("A draw."),
+        None => {}
+    }
+}
@@ -1,13 +1,19 @@
+use crate::ai::heuristic::OpenLinesEvaluator;
+use crate::ai::mcts::AIMcts;
 use crate::ai::minimax::AIMinimax;
 use crate::ai::AIStrategy;
 use crate::board::Board;
 use crate::controller::*;
+use crate::coordinates;
 use crate::GameState;
 use std::io;
+use std::path::PathBuf;
 use synonym::Synonym;
 
-pub struct Player<'a> {
-    pub name: &'a str,
+pub struct Player {
+    pub name: String,
+    /// The character drawn on the board for this player's pieces
+    pub symbol: char,
     pub player_piece: PlayerPiece,
     player_type: PlayerType,
     pub controller: Box<dyn PlayerController>,
@@ -39,30 +45,86 @@ impl std::ops::Deref for PlayerPiece {
     }
 }
 
-impl<'a> Player<'a> {
-    pub fn new(name: &'a str, player_piece: PlayerPiece, player_type: PlayerType) -> Player<'a> {
-        Player {
+/// Picks a symbol for the second player that does not clash with the first player's
+pub fn symbol_other_than(taken: char) -> char {
+    if taken == 'y' { 'x' } else { 'y' }
+}
+
+/// Creates the controller for an AI that plays with `strategy`
+pub fn ai_controller(strategy: AIStrategy) -> Box<dyn PlayerController> {
+    match strategy {
+        AIStrategy::Minimax(config) => {
+            Box::new(AIMinimax::new(config, Box::new(OpenLinesEvaluator)))
+        }
+        AIStrategy::Mcts(config) => Box::new(AIMcts::new(config)),
+    }
+}
+
+impl Player {
+    /// Creates a player who plays at this computer, at the keyboard
+    pub fn local(name: &str, symbol: char, player_piece: PlayerPiece) -> Player {
+        Player::with_controller(
             name,
+            symbol,
+            player_piece,
+            PlayerType::Local,
+            Box::new(LocalPlayer {}),
+        )
+    }
+
+    /// Creates an AI player that plays with `strategy`
+    pub fn ai(name: &str, symbol: char, player_piece: PlayerPiece, strategy: AIStrategy) -> Player {
+        Player::with_controller(
+            name,
+            symbol,
+            player_piece,
+            PlayerType::AI(strategy),
+            ai_controller(strategy),
+        )
+    }
+
+    /// Creates a player who plays somewhere else, e.g. at another computer or in a browser. Their moves come in
+    /// through `controller`, which is set up with whatever connection they are on.
+    pub fn remote(
+        name: &str,
+        symbol: char,
+        player_piece: PlayerPiece,
+        controller: Box<dyn PlayerController>,
+    ) -> Player {
+        Player::with_controller(name, symbol, player_piece, PlayerType::Remote, controller)
+    }
+
+    fn with_controller(
+        name: &str,
+        symbol: char,
+        player_piece: PlayerPiece,
+        player_type: PlayerType,
+        controller: Box<dyn PlayerController>,
+    ) -> Player {
+        Player {
+            name: name.to_string(),
+            symbol,
             player_piece,
             player_type,
-            controller: match player_type {
-                PlayerType::Local => Box::new(LocalPlayer {}),
-                PlayerType::AI(AIStrategy::Minimax) => Box::new(AIMinimax::new()),
-                PlayerType::Remote => {
-                    println!("Multiplayer not supported.");
-                    Box::new(AIMinimax::new())
-                }
-            },
+            controller,
         }
     }
 
+    pub fn player_type(&self) -> PlayerType {
+        self.player_type
+    }
+
     pub fn is_ai(&self) -> bool {
-        self.player_type != PlayerType::Local && self.player_type != PlayerType::Remote
+        matches!(self.player_type, PlayerType::AI(_))
     }
 
     pub fn is_local(&self) -> bool {
         self.player_type == PlayerType::Local
     }
+
+    pub fn is_remote(&self) -> bool {
+        self.player_type == PlayerType::Remote
+    }
 }
 
 impl InputController for LocalPlayer {
@@ -73,10 +135,20 @@ impl InputController for LocalPlayer {
     }
 
     fn parse_input(&self, input: &str, board_info: &Board) -> Result<InputType, InputError> {
+        // File names keep their case, so we only look at the command itself
+        if let Some((command, file)) = input.split_once(' ') {
+            match command.to_lowercase().as_str() {
+                "save" => return Ok(InputType::Save(PathBuf::from(file.trim()))),
+                "load" => return Ok(InputType::Load(PathBuf::from(file.trim()))),
+                _ => {}
+            }
+        }
         match input.to_lowercase().as_str() {
             "help" => Ok(InputType::Help),
             "exit" => Ok(InputType::Exit),
             "restart" => Ok(InputType::Restart),
+            "undo" => Ok(InputType::Undo),
+            "redo" => Ok(InputType::Redo),
             val if val.parse::<usize>().is_ok() => {
                 let parsed_number = val.parse::<usize>().expect("Could not parse input value.");
 
@@ -85,7 +157,14 @@ impl InputController for LocalPlayer {
                     Err(e) => Err(InputError::InvalidBoardError(e)),
                 }
             }
-            _ => Err(InputError::InvalidCommand),
+            val => match coordinates::parse(val, board_info) {
+                Some(Ok(index)) => match board_info.is_valid_move(index) {
+                    Ok(_) => Ok(InputType::Coord(index)),
+                    Err(_) => Err(InputError::CellTaken(val.to_string())),
+                },
+                Some(Err(e)) => Err(e),
+                None => Err(InputError::InvalidCommand),
+            },
         }
     }
 }
@@ -93,10 +172,20 @@ impl InputController for LocalPlayer {
 impl PlayerController for LocalPlayer {
     fn handle_input(&self, game_state: &GameState) -> Result<InputType, InputError> {
         println!();
+        // With two people at the keyboard, they need to know whose turn it is
+        if game_state.players().get_local_human_players().count() > 1 {
+            let player = game_state.current_player();
+            println!("It's {}'s turn, playing {}.", player.name, player.symbol);
+        }
         println!("What do you want to do?");
-        println!("Type a number from 0 to 8 to make your choice.");
+        println!(
+            "Type a number from 0 to {}, or a coordinate like b2, 2,3 or 'row 2 col 3', to make your choice.",
+            game_state.board().len() - 1
+        );
         println!("Type 'help' for assistance on how to designate the board.");
         println!("Type 'restart' to restart.");
+        println!("Type 'undo' to take back a move, and 'redo' to play it again.");
+        println!("Type 'save <file>' to save the game, and 'load <file>' to go on with a saved one.");
         println!("Type 'exit' to quit.");
         self.parse_input(&self.get_raw_input(), game_state.board())
     }
@@ -1,19 +1,21 @@
+use crate::ai::minimax::MinimaxConfig;
 use crate::ai::AIStrategy;
+use crate::controller::PlayerController;
 use crate::player::base_player::*;
 
-pub struct PlayerList<'a> {
-    player_1: Player<'a>,
-    player_2: Player<'a>,
+pub struct PlayerList {
+    player_1: Player,
+    player_2: Player,
 }
 
 pub struct PlayerListIterator<'a> {
-    players: &'a PlayerList<'a>,
+    players: &'a PlayerList,
     index: usize,
 }
 
 // I am doing this primarily for fun and learning, and so I can iterate over players
 impl<'a> Iterator for PlayerListIterator<'a> {
-    type Item = &'a Player<'a>;
+    type Item = &'a Player;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.index {
@@ -30,60 +32,63 @@ impl<'a> Iterator for PlayerListIterator<'a> {
     }
 }
 
-impl<'a> Default for PlayerList<'a> {
+impl Default for PlayerList {
     fn default() -> Self {
-        PlayerList {
-            player_1: Player::new("x", PlayerPiece::new(1), PlayerType::Local),
-            player_2: Player::new(
-                "y",
-                PlayerPiece::new(2),
-                PlayerType::AI(AIStrategy::Minimax),
-            ),
-        }
+        PlayerList::against_ai(AIStrategy::Minimax(MinimaxConfig::default()))
     }
 }
 
-impl<'a> PlayerList<'a> {
-    /// This is currently wrapped in an Option, to reflect the potential for multiplayer in the future
-    pub fn get_ai_player(&self) -> Option<&Player> {
-        let ai_players = self.iter().filter(|p| p.is_ai()).collect::<Vec<&Player>>();
+impl PlayerList {
+    /// Creates a player list where `player_1` moves first
+    pub fn new(player_1: Player, player_2: Player) -> PlayerList {
+        PlayerList { player_1, player_2 }
+    }
 
-        if ai_players.is_empty() {
-            None
-        } else {
-            Some(
-                ai_players
-                    .first()
-                    .expect("Unrecoverable error. AI Player exists but can't be found."),
-            )
+    /// Pairs a local human player, who goes first, with an AI player using the given strategy
+    pub fn against_ai(strategy: AIStrategy) -> PlayerList {
+        PlayerList::new(
+            Player::local("x", 'x', PlayerPiece::new(1)),
+            Player::ai("y", 'y', PlayerPiece::new(2), strategy),
+        )
+    }
+
+    /// Returns the local human players, i.e. everyone sharing this terminal
+    pub fn get_local_human_players(&self) -> impl Iterator<Item = &Player> {
+        self.iter().filter(|p| p.is_local())
+    }
+
+    /// Gives every local human player a new controller made by `make`
+    pub fn set_local_controllers(&mut self, make: impl Fn() -> Box<dyn PlayerController>) {
+        for player in [&mut self.player_1, &mut self.player_2] {
+            if player.is_local() {
+                player.controller = make();
+            }
         }
     }
 
-    pub fn get_local_human_player(&self) -> &Player {
-        let local_player = self
-            .iter()
-            .filter(|p| p.is_local())
-            .collect::<Vec<&Player>>();
+    /// Returns all human players, whether they sit at this computer or at another one
+    pub fn get_human_players(&self) -> impl Iterator<Item = &Player> {
+        self.iter().filter(|p| p.is_local() || p.is_remote())
+    }
 
-        if local_player.is_empty() {
-            panic!(
-                "Well. That's awkward. No local human player found, which should not be possible."
-            )
+    /// Returns the piece of the player who moves after the player with the given piece
+    pub fn next_piece_after(&self, piece: PlayerPiece) -> PlayerPiece {
+        if piece == self.player_1.player_piece {
+            self.player_2.player_piece
         } else {
-            local_player.first().unwrap()
+            self.player_1.player_piece
         }
     }
 
-    pub fn get_ai_player_piece(&self) -> Option<PlayerPiece> {
-        self.get_ai_player().map(|p| p.player_piece)
+    pub fn len(&self) -> usize {
+        self.iter().count()
     }
 
-    /// Returns the local human player's piece. This is assumed to always exist.
-    pub fn get_local_human_player_piece(&self) -> PlayerPiece {
-        self.get_local_human_player().player_piece
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
     }
 
-    pub fn iter(&self) -> PlayerListIterator {
+    pub fn iter(&self) -> PlayerListIterator<'_> {
         PlayerListIterator {
             players: self,
             index: 0,
@@ -0,0 +1,424 @@
+//! Drawing the board. The game decides when to draw it, and a `Renderer` decides what it looks like.
+//!
+//! Renderers write into anything that implements `fmt::Write`, so a board can be drawn into a `String` as easily as
+//! onto the terminal.
+
+use crate::board::{Board, CellState};
+use crate::coordinates;
+use crate::player::playerlist::PlayerList;
+use serde::Deserialize;
+use std::fmt;
+
+pub trait Renderer {
+    /// Draws the board with every player's symbol on it
+    fn render_board(
+        &self,
+        out: &mut dyn fmt::Write,
+        board: &Board,
+        players: &PlayerList,
+    ) -> fmt::Result;
+
+    /// Draws the board with the number players type for each cell
+    fn render_help(&self, out: &mut dyn fmt::Write, board: &Board) -> fmt::Result;
+}
+
+/// The classic look, with `|` between cells and a line of `_` beneath every row
+pub struct AsciiRenderer;
+
+/// Lines drawn with Unicode box-drawing characters, which look a lot tidier in most terminals
+pub struct UnicodeRenderer;
+
+/// The classic look, with each player's symbol in a colour of its own
+pub struct AnsiRenderer {
+    /// In the order the players move
+    pub colours: [Colour; 2],
+}
+
+/// The ways of drawing the board that can be picked on the command line or in the config file
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    #[default]
+    Ascii,
+    Unicode,
+    #[serde(rename = "color", alias = "colour")]
+    Ansi,
+}
+
+/// The colours a player's symbol can be drawn in
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colour {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+/// The colours the players get unless they pick their own, in the order the players move
+pub const DEFAULT_COLOURS: [Colour; 2] = [Colour::Red, Colour::Blue];
+
+impl Style {
+    /// Creates the renderer for this style. Only the colour style uses `colours`.
+    pub fn renderer(self, colours: [Colour; 2]) -> Box<dyn Renderer> {
+        match self {
+            Style::Ascii => Box::new(AsciiRenderer),
+            Style::Unicode => Box::new(UnicodeRenderer),
+            Style::Ansi => Box::new(AnsiRenderer { colours }),
+        }
+    }
+}
+
+impl Colour {
+    /// The escape code that turns text bold and this colour
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Colour::Red => "\x1b[1;31m",
+            Colour::Green => "\x1b[1;32m",
+            Colour::Yellow => "\x1b[1;33m",
+            Colour::Blue => "\x1b[1;34m",
+            Colour::Magenta => "\x1b[1;35m",
+            Colour::Cyan => "\x1b[1;36m",
+        }
+    }
+}
+
+impl Default for AnsiRenderer {
+    fn default() -> Self {
+        AnsiRenderer {
+            colours: DEFAULT_COLOURS,
+        }
+    }
+}
+
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+impl Renderer for AsciiRenderer {
+    fn render_board(
+        &self,
+        out: &mut dyn fmt::Write,
+        board: &Board,
+        players: &PlayerList,
+    ) -> fmt::Result {
+        let rows = cells(board, |cell| symbol(cell, players).to_string());
+        ascii_grid(out, &rows, 1)
+    }
+
+    fn render_help(&self, out: &mut dyn fmt::Write, board: &Board) -> fmt::Result {
+        let cell_width = number_width(board);
+        let rows = numbers(board, |number| format!("{number:>cell_width$}"));
+        ascii_grid(out, &rows, cell_width)
+    }
+}
+
+impl Renderer for UnicodeRenderer {
+    fn render_board(
+        &self,
+        out: &mut dyn fmt::Write,
+        board: &Board,
+        players: &PlayerList,
+    ) -> fmt::Result {
+        let rows = cells(board, |cell| symbol(cell, players).to_string());
+        box_grid(out, &rows, 1)
+    }
+
+    fn render_help(&self, out: &mut dyn fmt::Write, board: &Board) -> fmt::Result {
+        let cell_width = number_width(board);
+        let rows = numbers(board, |number| format!("{number:>cell_width$}"));
+        box_grid(out, &rows, cell_width)
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn render_board(
+        &self,
+        out: &mut dyn fmt::Write,
+        board: &Board,
+        players: &PlayerList,
+    ) -> fmt::Result {
+        let rows = cells(board, |cell| match cell {
+            CellState::Empty => " ".to_string(),
+            CellState::Player(piece) => {
+                let position = players
+                    .iter()
+                    .position(|player| player.player_piece == piece)
+                    .unwrap_or(0);
+                let colour = self.colours[position % self.colours.len()].ansi_code();
+                format!("{colour}{}{RESET}", symbol(cell, players))
+            }
+        });
+        ascii_grid(out, &rows, 1)
+    }
+
+    fn render_help(&self, out: &mut dyn fmt::Write, board: &Board) -> fmt::Result {
+        let cell_width = number_width(board);
+        let rows = numbers(board, |number| format!("{DIM}{number:>cell_width$}{RESET}"));
+        ascii_grid(out, &rows, cell_width)
+    }
+}
+
+/// Prints the board on the terminal, the way `renderer` draws it
+pub fn print_board(renderer: &dyn Renderer, board: &Board, players: &PlayerList) {
+    let mut out = String::new();
+    renderer
+        .render_board(&mut out, board, players)
+        .expect("Writing to a String never fails");
+    println!();
+    println!("The board currently looks like this:");
+    print!("{out}");
+    println!();
+}
+
+/// Prints the cell numbers on the terminal, the way `renderer` draws them
+pub fn print_help(renderer: &dyn Renderer, board: &Board) {
+    let mut out = String::new();
+    renderer
+        .render_help(&mut out, board)
+        .expect("Writing to a String never fails");
+    println!();
+    println!("This is how you designate the board cells:");
+    print!("{out}");
+    println!();
+}
+
+/// The text of every cell, row by row
+fn cells(board: &Board, text: impl Fn(CellState) -> String) -> Vec<Vec<String>> {
+    board
+        .get_all_rows()
+        .iter()
+        .map(|row| row.iter().map(|cell| text(*cell)).collect())
+        .collect()
+}
+
+/// The cell numbers, row by row, counting from the top left
+fn numbers(board: &Board, text: impl Fn(usize) -> String) -> Vec<Vec<String>> {
+    (0..board.height())
+        .map(|row| {
+            (0..board.width())
+                .map(|col| text(row * board.width() + col))
+                .collect()
+        })
+        .collect()
+}
+
+/// Larger boards have cell numbers with more than one digit, so we pad every cell to the widest one
+fn number_width(board: &Board) -> usize {
+    (board.len() - 1).to_string().len()
+}
+
+fn symbol(cell: CellState, players: &PlayerList) -> char {
+    match cell {
+        CellState::Empty => ' ',
+        CellState::Player(piece) => players
+            .iter()
+            .find(|player| player.player_piece == piece)
+            .map_or('?', |player| player.symbol),
+    }
+}
+
+/// Draws rows of cells that are `cell_width` characters wide, not counting any colour codes, with the column letters
+/// above them and the row numbers beside them, e.g. for a 3x3 board:
+///
+/// ```text
+///   a   b   c
+/// 1 x |   | y
+///   __|___|___
+/// ```
+fn ascii_grid(out: &mut dyn fmt::Write, rows: &[Vec<String>], cell_width: usize) -> fmt::Result {
+    let margin = margin(rows);
+    writeln!(out, "{margin}{}", column_letters(rows, cell_width))?;
+    for (row_index, row) in rows.iter().enumerate() {
+        writeln!(out, "{} {}", row_number(rows, row_index), row.join(" | "))?;
+        let first_cell = "_".repeat(cell_width + 1);
+        let other_cells = format!("|{}", "_".repeat(cell_width + 2)).repeat(row.len() - 1);
+        writeln!(out, "{margin}{first_cell}{other_cells}")?;
+    }
+    Ok(())
+}
+
+/// Draws rows of cells with box-drawing lines between them, with the column letters above them and the row numbers
+/// beside them, e.g. for a 3x3 board:
+///
+/// ```text
+///    a   b   c
+/// 1  x │   │ y
+///   ───┼───┼───
+/// 2    │ x │
+/// ```
+fn box_grid(out: &mut dyn fmt::Write, rows: &[Vec<String>], cell_width: usize) -> fmt::Result {
+    let margin = margin(rows);
+    writeln!(out, "{margin} {}", column_letters(rows, cell_width))?;
+    for (row_index, row) in rows.iter().enumerate() {
+        if row_index > 0 {
+            let line = vec!["─".repeat(cell_width + 2); row.len()].join("┼");
+            writeln!(out, "{margin}{line}")?;
+        }
+        writeln!(out, "{}  {} ", row_number(rows, row_index), row.join(" │ "))?;
+    }
+    Ok(())
+}
+
+/// The letters of the columns, each lined up with the right edge of its cells
+fn column_letters(rows: &[Vec<String>], cell_width: usize) -> String {
+    let columns = rows.first().map_or(0, Vec::len);
+    (0..columns)
+        .map(|col| format!("{:>cell_width$}", coordinates::column_label(col)))
+        .collect::<Vec<_>>()
+        .join("   ")
+}
+
+/// The row number in front of the row at `row_index`, padded so every row starts in the same place
+fn row_number(rows: &[Vec<String>], row_index: usize) -> String {
+    let width = rows.len().to_string().len();
+    format!("{:>width$}", row_index + 1)
+}
+
+/// The space in front of the lines without a row number
+fn margin(rows: &[Vec<String>]) -> String {
+    " ".repeat(rows.len().to_string().len() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::base_player::{Player, PlayerPiece};
+
+    fn player(symbol: char, piece: u8) -> Player {
+        Player::local(&symbol.to_string(), symbol, PlayerPiece::new(piece))
+    }
+
+    fn players() -> PlayerList {
+        PlayerList::new(player('x', 1), player('o', 2))
+    }
+
+    /// A 3x3 board with x in the top left corner and the middle, and o in the top right corner
+    fn board() -> Board {
+        let mut board = Board::new(3, 3).unwrap();
+        for (index, piece) in [(0, 1), (2, 2), (4, 1)] {
+            board.place(index, PlayerPiece::new(piece)).unwrap();
+        }
+        board
+    }
+
+    fn render_board(renderer: &dyn Renderer, players: &PlayerList) -> String {
+        let mut out = String::new();
+        renderer.render_board(&mut out, &board(), players).unwrap();
+        out
+    }
+
+    /// The cell numbers of a 4x4 board, which go up to two digits
+    fn render_help(renderer: &dyn Renderer) -> String {
+        let mut out = String::new();
+        renderer
+            .render_help(&mut out, &Board::new(4, 4).unwrap())
+            .unwrap();
+        out
+    }
+
+    /// The text a renderer writes for these lines. Each line is in quotes of its own, so the spaces at the end of it are easy to see.
+    fn lines(lines: &[&str]) -> String {
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    #[test]
+    fn ascii() {
+        assert_eq!(
+            render_board(&AsciiRenderer, &players()),
+            lines(&[
+                "  a   b   c",
+                "1 x |   | o",
+                "  __|___|___",
+                "2   | x |  ",
+                "  __|___|___",
+                "3   |   |  ",
+                "  __|___|___",
+            ])
+        );
+        assert_eq!(
+            render_help(&AsciiRenderer),
+            lines(&[
+                "   a    b    c    d",
+                "1  0 |  1 |  2 |  3",
+                "  ___|____|____|____",
+                "2  4 |  5 |  6 |  7",
+                "  ___|____|____|____",
+                "3  8 |  9 | 10 | 11",
+                "  ___|____|____|____",
+                "4 12 | 13 | 14 | 15",
+                "  ___|____|____|____",
+            ])
+        );
+    }
+
+    #[test]
+    fn unicode() {
+        assert_eq!(
+            render_board(&UnicodeRenderer, &players()),
+            lines(&[
+                "   a   b   c",
+                "1  x │   │ o ",
+                "  ───┼───┼───",
+                "2    │ x │   ",
+                "  ───┼───┼───",
+                "3    │   │   ",
+            ])
+        );
+        assert_eq!(
+            render_help(&UnicodeRenderer),
+            lines(&[
+                "    a    b    c    d",
+                "1   0 │  1 │  2 │  3 ",
+                "  ────┼────┼────┼────",
+                "2   4 │  5 │  6 │  7 ",
+                "  ────┼────┼────┼────",
+                "3   8 │  9 │ 10 │ 11 ",
+                "  ────┼────┼────┼────",
+                "4  12 │ 13 │ 14 │ 15 ",
+            ])
+        );
+    }
+
+    #[test]
+    fn ansi() {
+        assert_eq!(
+            render_board(&AnsiRenderer::default(), &players()),
+            lines(&[
+                "  a   b   c",
+                "1 \x1b[1;31mx\x1b[0m |   | \x1b[1;34mo\x1b[0m",
+                "  __|___|___",
+                "2   | \x1b[1;31mx\x1b[0m |  ",
+                "  __|___|___",
+                "3   |   |  ",
+                "  __|___|___",
+            ])
+        );
+        assert_eq!(
+            render_help(&AnsiRenderer::default()),
+            lines(&[
+                "   a    b    c    d",
+                "1 \x1b[2m 0\x1b[0m | \x1b[2m 1\x1b[0m | \x1b[2m 2\x1b[0m | \x1b[2m 3\x1b[0m",
+                "  ___|____|____|____",
+                "2 \x1b[2m 4\x1b[0m | \x1b[2m 5\x1b[0m | \x1b[2m 6\x1b[0m | \x1b[2m 7\x1b[0m",
+                "  ___|____|____|____",
+                "3 \x1b[2m 8\x1b[0m | \x1b[2m 9\x1b[0m | \x1b[2m10\x1b[0m | \x1b[2m11\x1b[0m",
+                "  ___|____|____|____",
+                "4 \x1b[2m12\x1b[0m | \x1b[2m13\x1b[0m | \x1b[2m14\x1b[0m | \x1b[2m15\x1b[0m",
+                "  ___|____|____|____",
+            ])
+        );
+    }
+
+    #[test]
+    fn colours_go_in_the_order_the_players_move() {
+        let players = PlayerList::new(player('o', 2), player('x', 1));
+        let renderer = AnsiRenderer {
+            colours: [Colour::Green, Colour::Magenta],
+        };
+        let out = render_board(&renderer, &players);
+        assert!(out.contains("\x1b[1;32mo\x1b[0m"));
+        assert!(out.contains("\x1b[1;35mx\x1b[0m"));
+    }
+}
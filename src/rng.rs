@@ -0,0 +1,66 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, seedable pseudo-random number generator (SplitMix64).
+///
+/// It is nowhere near good enough for cryptography, but more than good enough to make the AI unpredictable.
+/// Giving it the same seed makes it produce the same numbers every time, which is handy for testing.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Creates a generator with the given seed, or one based on the current time if there is none
+    pub fn from_seed_or_time(seed: Option<u64>) -> Rng {
+        Rng::new(seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default()
+        }))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from 0 up to, but not including, `upper`
+    pub fn below(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(seed: u64) -> Vec<u64> {
+        let mut rng = Rng::new(seed);
+        (0..100).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        assert_eq!(numbers(42), numbers(42));
+        assert_eq!(
+            Rng::from_seed_or_time(Some(42)).next_u64(),
+            Rng::new(42).next_u64()
+        );
+        assert_ne!(numbers(42), numbers(43));
+    }
+
+    #[test]
+    fn below_stays_below() {
+        let mut rng = Rng::new(7);
+        for upper in 1..50 {
+            assert!(rng.below(upper) < upper);
+        }
+    }
+}
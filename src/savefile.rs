@@ -0,0 +1,489 @@
+//! Saving a game to a file, and picking it up again later.
+//!
+//! A saved game is plain text, with one setting per line. It starts with the version of the format, and the board and
+//! the rules. Then come the players, in the order they move, each with the settings that belong to them. Last come
+//! whose turn it is, and every move of the current game with the time it was made, in seconds since the Unix epoch.
+//!
+//! ```text
+//! tictactoe-save 1
+//! board 3 3                  Width and height
+//! win-length 3               How many pieces in a row it takes to win
+//! player 1                   The player's piece. Everything up to the next player line is about this player.
+//! name Ada Lovelace
+//! symbol x
+//! type local                 local, minimax or mcts
+//! player 2
+//! name y
+//! symbol y
+//! type minimax
+//! max-depth 4                How many moves ahead the AI looks, or none to let it decide
+//! time-budget-ms 500         How long it may think about each move, or none for no limit
+//! blunder-percent 5          How often it makes a random move instead
+//! seed none                  The seed for its random numbers, or none to pick one from the clock
+//! to-move 1                  The piece of the player whose turn it is
+//! move 1 4 1760000000        The piece that moved, the cell it went on, and when
+//! move 2 0 1760000012
+//! ```
+//!
+//! A Monte Carlo AI has `playouts` and `seed` instead of the minimax settings. AI settings that are left out get their
+//! usual defaults. Blank lines and lines starting with `#` are skipped.
+//!
+//! The version goes up whenever the format changes in a way older programs can't read. Newer programs keep reading
+//! every older version, so a saved game still loads after an upgrade.
+
+use crate::GameState;
+use crate::ai::AIStrategy;
+use crate::ai::mcts::MctsConfig;
+use crate::ai::minimax::MinimaxConfig;
+use crate::gamestate::GameReferee;
+use crate::network;
+use crate::player::base_player::{Player, PlayerPiece, PlayerType};
+use crate::player::playerlist::PlayerList;
+use std::fmt;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The version of the format we write. We can read every version up to this one.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// Players at other computers have their own copy of the game, which would go on without us
+    NetworkGame,
+    /// The file was saved by a newer version of the game
+    NewerVersion(u32),
+    /// Something is wrong on this line of the file
+    Invalid(usize, String),
+    /// The file leaves out something we need
+    Missing(&'static str),
+    /// The game in the file was already won or drawn, so there is nothing left to play
+    Finished,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "Could not read or write the file: {e}"),
+            SaveError::NetworkGame => write!(f, "Network games can't be saved."),
+            SaveError::NewerVersion(version) => write!(
+                f,
+                "The game was saved in version {version} of the format, but we only know up to version {FORMAT_VERSION}. Try a newer version of the game."
+            ),
+            SaveError::Invalid(line, reason) => {
+                write!(f, "Line {line} of the saved game: {reason}")
+            }
+            SaveError::Missing(what) => write!(f, "The saved game has no {what}."),
+            SaveError::Finished => write!(f, "The saved game is already over."),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+
+/// Saves the game to `path`, replacing whatever was there
+pub fn save(game: &GameState, path: &Path) -> Result<(), SaveError> {
+    fs::write(path, write(game)?)?;
+    Ok(())
+}
+
+/// Loads a saved game from `path`, ready to be played on from where it was left
+pub fn load(path: &Path) -> Result<GameState, SaveError> {
+    read(&fs::read_to_string(path)?)
+}
+
+/// Writes the game in the current version of the format
+pub fn write(game: &GameState) -> Result<String, SaveError> {
+    let board = game.board();
+    let mut text = format!("tictactoe-save {FORMAT_VERSION}\n");
+    // Writing to a String never fails
+    let _ = writeln!(text, "board {} {}", board.width(), board.height());
+    let _ = writeln!(text, "win-length {}", game.referee().win_length());
+    for player in game.players().iter() {
+        let _ = writeln!(text, "player {}", *player.player_piece);
+        let _ = writeln!(text, "name {}", player.name);
+        let _ = writeln!(text, "symbol {}", player.symbol);
+        match player.player_type() {
+            PlayerType::Local => text.push_str("type local\n"),
+            PlayerType::AI(AIStrategy::Minimax(config)) => {
+                text.push_str("type minimax\n");
+                let _ = writeln!(text, "max-depth {}", optional(config.max_depth));
+                let time_budget = config.time_budget.map(|budget| budget.as_millis());
+                let _ = writeln!(text, "time-budget-ms {}", optional(time_budget));
+                let _ = writeln!(text, "blunder-percent {}", config.blunder_percent);
+                let _ = writeln!(text, "seed {}", optional(config.seed));
+            }
+            PlayerType::AI(AIStrategy::Mcts(config)) => {
+                text.push_str("type mcts\n");
+                let _ = writeln!(text, "playouts {}", config.playouts);
+                let _ = writeln!(text, "seed {}", optional(config.seed));
+            }
+            PlayerType::Remote => return Err(SaveError::NetworkGame),
+        }
+    }
+    let _ = writeln!(text, "to-move {}", *game.piece_to_move());
+    for played in game.history() {
+        let played_at = played
+            .played_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        let _ = writeln!(text, "move {} {} {played_at}", *played.piece, played.index);
+    }
+    Ok(text)
+}
+
+/// A player as it was saved, before we know all of their settings
+struct SavedPlayer {
+    piece: u8,
+    name: Option<String>,
+    /// The symbol, and the line it is on
+    symbol: Option<(usize, char)>,
+    player_type: Option<PlayerType>,
+}
+
+/// Reads a saved game in any version of the format up to the current one
+pub fn read(text: &str) -> Result<GameState, SaveError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let Some((number, first_line)) = lines.next() else {
+        return Err(SaveError::Missing("version"));
+    };
+    let version = match first_line.split_once(' ') {
+        Some(("tictactoe-save", version)) => parse(number, version.trim(), "version")?,
+        _ => {
+            return Err(SaveError::Invalid(
+                number,
+                "This is not a saved game.".to_string(),
+            ));
+        }
+    };
+    match version {
+        0 => {
+            return Err(SaveError::Invalid(
+                number,
+                "There is no version 0 of the format.".to_string(),
+            ));
+        }
+        1..=FORMAT_VERSION => {}
+        _ => return Err(SaveError::NewerVersion(version)),
+    }
+
+    let mut size = None;
+    let mut win_length = None;
+    let mut players: Vec<SavedPlayer> = Vec::new();
+    let mut to_move = None;
+    let mut moves = Vec::new();
+    for (number, line) in lines {
+        let (key, value) = line
+            .split_once(' ')
+            .map_or((line, ""), |(key, value)| (key, value.trim()));
+        match key {
+            "board" => {
+                let Some((width, height)) = value.split_once(' ') else {
+                    return Err(SaveError::Invalid(
+                        number,
+                        "The board needs a width and a height.".to_string(),
+                    ));
+                };
+                size = Some((
+                    parse(number, width, "width")?,
+                    parse(number, height.trim(), "height")?,
+                ));
+            }
+            "win-length" => win_length = Some(parse(number, value, "win length")?),
+            "player" => players.push(SavedPlayer {
+                piece: parse(number, value, "piece")?,
+                name: None,
+                symbol: None,
+                player_type: None,
+            }),
+            "to-move" => to_move = Some((number, parse::<u8>(number, value, "piece")?)),
+            "move" => {
+                let fields: Vec<&str> = value.split_whitespace().collect();
+                let [piece, index, played_at] = fields[..] else {
+                    return Err(SaveError::Invalid(
+                        number,
+                        "A move needs a piece, a cell and a time.".to_string(),
+                    ));
+                };
+                let played_at = UNIX_EPOCH + Duration::from_secs(parse(number, played_at, "time")?);
+                moves.push((
+                    number,
+                    parse::<u8>(number, piece, "piece")?,
+                    parse::<usize>(number, index, "cell")?,
+                    played_at,
+                ));
+            }
+            _ => {
+                let Some(player) = players.last_mut() else {
+                    return Err(SaveError::Invalid(
+                        number,
+                        format!("{key} is not a setting we know."),
+                    ));
+                };
+                read_player_setting(player, number, key, value)?;
+            }
+        }
+    }
+
+    let (width, height) = size.ok_or(SaveError::Missing("board"))?;
+    let win_length = win_length.ok_or(SaveError::Missing("win length"))?;
+    let Some((board, win_length)) = network::check_rules(width, height, win_length) else {
+        return Err(SaveError::Missing("board we can play on"));
+    };
+    let [first, second] =
+        <[SavedPlayer; 2]>::try_from(players).map_err(|_| SaveError::Missing("pair of players"))?;
+    if first.piece == second.piece {
+        return Err(SaveError::Missing("pair of players with different pieces"));
+    }
+    if let (Some((_, first_symbol)), Some((number, second_symbol))) = (first.symbol, second.symbol)
+        && first_symbol == second_symbol
+    {
+        return Err(SaveError::Invalid(
+            number,
+            format!(
+                "Player {} already uses {first_symbol} as their symbol.",
+                first.piece
+            ),
+        ));
+    }
+    let players = PlayerList::new(build_player(first)?, build_player(second)?);
+    let mut game = GameState::new(board, GameReferee::new(win_length), players);
+
+    for (number, piece, index, played_at) in moves {
+        if piece != *game.piece_to_move() {
+            return Err(SaveError::Invalid(
+                number,
+                format!(
+                    "It was player {}'s turn, not player {piece}'s.",
+                    *game.piece_to_move()
+                ),
+            ));
+        }
+        game.replay_move(index, played_at)
+            .map_err(|e| SaveError::Invalid(number, e.to_string()))?;
+    }
+    let (number, to_move) = to_move.ok_or(SaveError::Missing("player to move"))?;
+    if to_move != *game.piece_to_move() {
+        return Err(SaveError::Invalid(
+            number,
+            format!(
+                "After these moves it is player {}'s turn, not player {to_move}'s.",
+                *game.piece_to_move()
+            ),
+        ));
+    }
+    if game.outcome().is_some() {
+        return Err(SaveError::Finished);
+    }
+    Ok(game)
+}
+
+/// Reads a line about the most recent player. AI settings have to come after the type, so we know which AI they are for.
+fn read_player_setting(
+    player: &mut SavedPlayer,
+    number: usize,
+    key: &str,
+    value: &str,
+) -> Result<(), SaveError> {
+    let not_for_this_player = || {
+        SaveError::Invalid(
+            number,
+            format!("{key} is not a setting for player {}.", player.piece),
+        )
+    };
+    match (key, &mut player.player_type) {
+        ("name", _) => player.name = Some(value.to_string()),
+        ("symbol", _) => player.symbol = Some((number, parse(number, value, "symbol")?)),
+        ("type", _) => {
+            player.player_type = Some(match value {
+                "local" => PlayerType::Local,
+                "minimax" => PlayerType::AI(AIStrategy::Minimax(MinimaxConfig::default())),
+                "mcts" => PlayerType::AI(AIStrategy::Mcts(MctsConfig::default())),
+                _ => {
+                    return Err(SaveError::Invalid(
+                        number,
+                        format!("{value} is not a type of player we know."),
+                    ));
+                }
+            });
+        }
+        ("max-depth", Some(PlayerType::AI(AIStrategy::Minimax(config)))) => {
+            config.max_depth = parse_optional(number, value, "depth")?;
+        }
+        ("time-budget-ms", Some(PlayerType::AI(AIStrategy::Minimax(config)))) => {
+            config.time_budget =
+                parse_optional(number, value, "time budget")?.map(Duration::from_millis);
+        }
+        ("blunder-percent", Some(PlayerType::AI(AIStrategy::Minimax(config)))) => {
+            config.blunder_percent = parse(number, value, "percentage")?;
+        }
+        ("seed", Some(PlayerType::AI(AIStrategy::Minimax(config)))) => {
+            config.seed = parse_optional(number, value, "seed")?;
+        }
+        ("playouts", Some(PlayerType::AI(AIStrategy::Mcts(config)))) => {
+            config.playouts = parse(number, value, "number of playouts")?;
+            if config.playouts < 1 {
+                return Err(SaveError::Invalid(
+                    number,
+                    "An AI has to play out at least one game.".to_string(),
+                ));
+            }
+        }
+        ("seed", Some(PlayerType::AI(AIStrategy::Mcts(config)))) => {
+            config.seed = parse_optional(number, value, "seed")?;
+        }
+        _ => return Err(not_for_this_player()),
+    }
+    Ok(())
+}
+
+fn build_player(saved: SavedPlayer) -> Result<Player, SaveError> {
+    let name = saved.name.ok_or(SaveError::Missing("player name"))?;
+    let (_, symbol) = saved.symbol.ok_or(SaveError::Missing("player symbol"))?;
+    let player_type = saved.player_type.ok_or(SaveError::Missing("player type"))?;
+    let piece = PlayerPiece::new(saved.piece);
+    match player_type {
+        PlayerType::Local => Ok(Player::local(&name, symbol, piece)),
+        PlayerType::AI(strategy) => Ok(Player::ai(&name, symbol, piece, strategy)),
+        // Only the types of players that can be saved are read back, so this is never in a file
+        PlayerType::Remote => Err(SaveError::NetworkGame),
+    }
+}
+
+fn optional(value: Option<impl fmt::Display>) -> String {
+    value.map_or("none".to_string(), |value| value.to_string())
+}
+
+fn parse<T: FromStr>(number: usize, value: &str, what: &str) -> Result<T, SaveError> {
+    value
+        .parse()
+        .map_err(|_| SaveError::Invalid(number, format!("{value} is not a valid {what}.")))
+}
+
+fn parse_optional<T: FromStr>(
+    number: usize,
+    value: &str,
+    what: &str,
+) -> Result<Option<T>, SaveError> {
+    match value {
+        "none" => Ok(None),
+        value => parse(number, value, what).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::minimax::MinimaxConfig;
+    use crate::render::AsciiRenderer;
+    use std::env;
+    use std::process;
+
+    /// A game between a local player and a minimax AI, with these moves
+    fn saved_game(to_move: u8, moves: &[(u8, usize)]) -> String {
+        let mut text = "tictactoe-save 1\n\
+            board 3 3\n\
+            win-length 3\n\
+            player 1\n\
+            name Ann\n\
+            symbol x\n\
+            type local\n\
+            player 2\n\
+            name Bob\n\
+            symbol o\n\
+            type minimax\n\
+            max-depth none\n\
+            time-budget-ms 500\n\
+            blunder-percent 0\n\
+            seed none\n"
+            .to_string();
+        let _ = writeln!(text, "to-move {to_move}");
+        for (piece, index) in moves {
+            let _ = writeln!(text, "move {piece} {index} 1760000000");
+        }
+        text
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let text = saved_game(1, &[(1, 4), (2, 0)]);
+        let game = read(&text).unwrap();
+        assert_eq!(game.moves(), vec![4, 0]);
+        assert_eq!(*game.piece_to_move(), 1);
+        assert_eq!(write(&game).unwrap(), text);
+    }
+
+    #[test]
+    fn rejects_a_won_game() {
+        let text = saved_game(2, &[(1, 0), (2, 3), (1, 1), (2, 4), (1, 2)]);
+        assert!(matches!(read(&text), Err(SaveError::Finished)));
+    }
+
+    #[test]
+    fn rejects_a_drawn_game() {
+        let moves = [0, 1, 2, 4, 3, 5, 7, 6, 8];
+        let moves: Vec<(u8, usize)> = moves
+            .iter()
+            .enumerate()
+            .map(|(turn, &index)| (turn as u8 % 2 + 1, index))
+            .collect();
+        let text = saved_game(2, &moves);
+        assert!(matches!(read(&text), Err(SaveError::Finished)));
+    }
+
+    #[test]
+    fn rejects_a_symbol_both_players_use() {
+        let text = saved_game(1, &[]).replace("symbol o", "symbol x");
+        assert!(matches!(read(&text), Err(SaveError::Invalid(10, _))));
+    }
+
+    #[test]
+    fn rejects_an_ai_that_plays_out_no_games() {
+        let minimax = "type minimax\n\
+            max-depth none\n\
+            time-budget-ms 500\n\
+            blunder-percent 0\n";
+        let text = saved_game(1, &[]).replace(minimax, "type mcts\nplayouts 0\n");
+        assert!(matches!(read(&text), Err(SaveError::Invalid(12, _))));
+        let text = saved_game(1, &[]).replace(minimax, "type mcts\nplayouts 1\n");
+        assert!(read(&text).is_ok());
+    }
+
+    #[test]
+    fn autosave_keeps_a_game_that_can_be_loaded() {
+        let ai = |name: &str, symbol: char, piece: u8| {
+            Player::ai(
+                name,
+                symbol,
+                PlayerPiece::new(piece),
+                AIStrategy::Minimax(MinimaxConfig::default()),
+            )
+        };
+        let players = PlayerList::new(ai("Ann", 'x', 1), ai("Bob", 'o', 2));
+        let board = crate::board::Board::new(3, 3).unwrap();
+        let mut game = GameState::new(board, GameReferee::new(3), players);
+        let path = env::temp_dir().join(format!("tictactoe-autosave-{}.txt", process::id()));
+        game.autosave_to(path.clone());
+        game.game_loop(&AsciiRenderer);
+        assert!(game.outcome().is_some());
+
+        let loaded = load(&path);
+        let _ = fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+        assert!(loaded.outcome().is_none());
+        assert_eq!(loaded.moves(), game.moves()[..game.moves().len() - 1]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::ai::AIStrategy;
+use crate::board::Board;
+use crate::controller::InputType;
+use crate::gamestate::{GameOutcome, GameReferee, GameState};
+use crate::player::base_player::{Player, PlayerPiece};
+use crate::player::playerlist::PlayerList;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// One side in a self-play match: an AI strategy, and the name it is reported under
+pub struct Contender {
+    pub name: String,
+    pub strategy: AIStrategy,
+}
+
+/// The tally of a self-play match, counted from the first contender's point of view
+#[derive(Default)]
+pub struct SelfPlayReport {
+    names: [String; 2],
+    games: u32,
+    wins: [u32; 2],
+    draws: u32,
+    total_moves: u32,
+    moves_made: [u32; 2],
+    time_spent: [Duration; 2],
+}
+
+/// Plays `games` games between two AIs without rendering anything, taking turns on who goes first.
+///
+/// The first contender always plays piece 1 and the second plays piece 2, whoever starts.
+pub fn play_matches(
+    contenders: &[Contender; 2],
+    games: u32,
+    board: &Board,
+    referee: GameReferee,
+) -> SelfPlayReport {
+    let mut report = SelfPlayReport {
+        names: [contenders[0].name.clone(), contenders[1].name.clone()],
+        ..Default::default()
+    };
+    for game_number in 0..games {
+        let player_1 = Player::ai(
+            &contenders[0].name,
+            'x',
+            PlayerPiece::new(1),
+            contenders[0].strategy,
+        );
+        let player_2 = Player::ai(
+            &contenders[1].name,
+            'y',
+            PlayerPiece::new(2),
+            contenders[1].strategy,
+        );
+        let players = if game_number.is_multiple_of(2) {
+            PlayerList::new(player_1, player_2)
+        } else {
+            PlayerList::new(player_2, player_1)
+        };
+        let mut game = GameState::new(board.clone(), referee, players);
+        let outcome = play_game(&mut game, &mut report);
+        report.record(outcome);
+    }
+    report
+}
+
+/// Plays a single game to the end, and returns how it ended
+fn play_game(game: &mut GameState, report: &mut SelfPlayReport) -> GameOutcome {
+    loop {
+        if let Some(outcome) = game.outcome() {
+            return outcome;
+        }
+        let player = game.current_player();
+        let contender = contender_index(player.player_piece);
+        let started = Instant::now();
+        let input = player.controller.handle_input(game);
+        report.time_spent[contender] += started.elapsed();
+        report.moves_made[contender] += 1;
+        match input {
+            Ok(InputType::Coord(coord)) => game
+                .place_piece(coord)
+                .expect("The AI should only suggest empty cells"),
+            _ => panic!("The AI should only ever suggest moves"),
+        }
+    }
+}
+
+/// Maps a piece to the contender playing it
+fn contender_index(piece: PlayerPiece) -> usize {
+    if *piece == 1 { 0 } else { 1 }
+}
+
+impl SelfPlayReport {
+    fn record(&mut self, outcome: GameOutcome) {
+        self.games += 1;
+        match outcome {
+            GameOutcome::Winner(piece) => self.wins[contender_index(piece)] += 1,
+            GameOutcome::Draw => self.draws += 1,
+        }
+        self.total_moves = self.moves_made.iter().sum();
+    }
+
+    fn average_time_per_move(&self, contender: usize) -> Duration {
+        if self.moves_made[contender] == 0 {
+            Duration::ZERO
+        } else {
+            self.time_spent[contender] / self.moves_made[contender]
+        }
+    }
+}
+
+impl fmt::Display for SelfPlayReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Played {} games of {} against {}.",
+            self.games, self.names[0], self.names[1]
+        )?;
+        writeln!(
+            f,
+            "{}: {} wins, {} losses, {} draws.",
+            self.names[0], self.wins[0], self.wins[1], self.draws
+        )?;
+        if self.games > 0 {
+            writeln!(
+                f,
+                "Average game length: {:.1} moves.",
+                self.total_moves as f64 / self.games as f64
+            )?;
+        }
+        for contender in 0..2 {
+            writeln!(
+                f,
+                "Average time per move for {}: {:.2?}.",
+                self.names[contender],
+                self.average_time_per_move(contender)
+            )?;
+        }
+        Ok(())
+    }
+}
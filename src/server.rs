@@ -0,0 +1,629 @@
+//! A game server that hosts many games at once, for players connecting with `--server`.
+//!
+//! Players connect like they would to a host (see the `network` module), but before the game starts they are in a lobby,
+//! where they can send:
+//!
+//! ```text
+//! LIST                    Lists the games waiting for an opponent, one `GAME <id> <width> <height> <win length> <name>` line each,
+//!                         and the games being played, one `LIVE <id> <description>` line each, followed by `END`
+//! CREATE 3 3 3            Creates a game with the given width, height and win length. The server answers `CREATED <id>`.
+//! ENTER 12                Joins game 12
+//! QUICK                   Joins the game that has waited the longest, or creates a standard game if there is none
+//! WATCH 12                Watches game 12 without playing in it
+//! ```
+//!
+//! Anything the server can't do is answered with `ERROR <reason>`, after which the player is still in the lobby.
+//! A player who hangs up while waiting for an opponent takes their game out of the lobby.
+//! Once both seats of a game are taken, the server sends both players the same `RULES`, `PLAYER` and `START` lines a host would.
+//! The player who created the game moves first.
+//!
+//! A player whose connection drops can connect again and send `RESUME <token>` instead of `JOIN`, with the token they got
+//! in `START`. The server then catches them up the same way a host would (see the `network` module).
+//!
+//! A spectator first gets the `RULES` and `PLAYER` lines, and then every move made so far as `MOVES 4 0 8`.
+//! After that they get every `MOVE` as it happens, `RESTART` when the players start over, and `EXIT` once the game is over.
+//!
+//! The server keeps its own copy of each game, and only passes a move on after checking it with `Board::is_valid_move`,
+//! so a player can't cheat by sending moves their own copy of the game would have refused.
+
+use crate::board::{BOARD_STANDARD_WIDTH, Board};
+use crate::controller::{GameEvent, InputType};
+use crate::gamestate::{GameReferee, GameState};
+use crate::network::connection::Connection;
+use crate::network::protocol::Message;
+use crate::network::reconnect::{self, ReconnectPolicy, Rejoin, Session};
+use crate::network::{self, NetworkError, Seat};
+use crate::player::playerlist::PlayerList;
+use crate::rng::Rng;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, SendError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The games waiting for a second player, and the games being played
+#[derive(Default)]
+pub struct Lobby {
+    next_id: u32,
+    open_games: Vec<OpenGame>,
+    running_games: Vec<(u32, Arc<Mutex<Audience>>)>,
+    /// The session tokens of the players in running games, and where to send them if they connect again
+    sessions: Vec<(String, Sender<Connection>)>,
+}
+
+/// A game that has been created, but not started, along with the player waiting for it to start
+struct OpenGame {
+    id: u32,
+    board: Board,
+    win_length: usize,
+    creator: Seat,
+    connection: Connection,
+}
+
+/// The spectators of a running game, along with what they need to catch up when they start watching
+struct Audience {
+    board: Board,
+    win_length: usize,
+    seats: [Seat; 2],
+    /// The moves made so far in the current game, in order
+    moves: Vec<usize>,
+    /// Every spectator has a thread of their own that passes these messages on, so a slow one can't hold up the game
+    spectators: Vec<Sender<Message>>,
+}
+
+/// Accepts players on `port` until the server is stopped, giving every player and every game a thread of its own.
+///
+/// `policy` decides what happens to players whose connection drops in the middle of a game.
+pub fn run(port: u16, policy: ReconnectPolicy) -> Result<(), NetworkError> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Listening for players on port {port}.");
+    serve(listener, policy);
+    Ok(())
+}
+
+/// Accepts players on `listener` until the server is stopped
+pub fn serve(listener: TcpListener, policy: ReconnectPolicy) {
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let lobby = Arc::clone(&lobby);
+        thread::spawn(move || {
+            if let Err(e) = welcome(stream, lobby, policy) {
+                println!("A player left the lobby: {e}");
+            }
+        });
+    }
+}
+
+/// Greets a new player, and keeps them in the lobby until they have created, joined or started watching a game
+fn welcome(
+    stream: TcpStream,
+    lobby: Arc<Mutex<Lobby>>,
+    policy: ReconnectPolicy,
+) -> Result<(), NetworkError> {
+    let mut connection = Connection::new(stream)?;
+    network::greet(&mut connection)?;
+    let seat = match connection.receive()? {
+        Message::Join { symbol, name } => Seat { name, symbol },
+        Message::Resume(token) => return resume(&token, connection, &lobby),
+        message => return Err(NetworkError::UnexpectedMessage(message)),
+    };
+    println!("{} entered the lobby.", seat.name);
+
+    loop {
+        match connection.receive()? {
+            Message::List => {
+                for listing in listings(&lobby) {
+                    connection.send(&listing)?;
+                }
+                connection.send(&Message::End)?;
+            }
+            Message::Create {
+                width,
+                height,
+                win_length,
+            } => match network::check_rules(width, height, win_length) {
+                Some((board, win_length)) => {
+                    return open(board, win_length, seat, connection, lobby, policy, false);
+                }
+                None => connection.send(&Message::Error(
+                    "The server can't host a game with those rules.".to_string(),
+                ))?,
+            },
+            Message::Enter(id) => {
+                let open_game = lobby
+                    .lock()
+                    .expect("The lobby lock is never poisoned")
+                    .take(Some(id));
+                match open_game {
+                    Some(open_game) => {
+                        return start_game(open_game, seat, connection, lobby, policy);
+                    }
+                    None => connection.send(&Message::Error(format!(
+                        "There is no game {id} waiting for an opponent."
+                    )))?,
+                }
+            }
+            Message::Quick => {
+                let open_game = lobby
+                    .lock()
+                    .expect("The lobby lock is never poisoned")
+                    .take(None);
+                match open_game {
+                    Some(open_game) => {
+                        return start_game(open_game, seat, connection, lobby, policy);
+                    }
+                    None => {
+                        let board = Board::new(BOARD_STANDARD_WIDTH, BOARD_STANDARD_WIDTH)
+                            .expect("The standard board is a valid board");
+                        return open(
+                            board,
+                            BOARD_STANDARD_WIDTH,
+                            seat,
+                            connection,
+                            lobby,
+                            policy,
+                            true,
+                        );
+                    }
+                }
+            }
+            Message::Watch(id) => {
+                let audience = lobby
+                    .lock()
+                    .expect("The lobby lock is never poisoned")
+                    .audience(id);
+                match audience {
+                    Some(audience) => {
+                        println!("{} is watching game {id}.", seat.name);
+                        return watch(audience, connection);
+                    }
+                    None => connection.send(&Message::Error(format!(
+                        "There is no game {id} being played right now."
+                    )))?,
+                }
+            }
+            message => connection.send(&Message::Error(format!(
+                "'{message}' can't be used in the lobby."
+            )))?,
+        }
+    }
+}
+
+/// Hands a player who connected again to the game they were in, which catches them up
+fn resume(
+    token: &str,
+    mut connection: Connection,
+    lobby: &Mutex<Lobby>,
+) -> Result<(), NetworkError> {
+    let sender = lobby
+        .lock()
+        .expect("The lobby lock is never poisoned")
+        .sessions
+        .iter()
+        .find(|(session_token, _)| session_token == token)
+        .map(|(_, sender)| sender.clone());
+    match sender {
+        Some(sender) => match sender.send(connection) {
+            Ok(()) => Ok(()),
+            // The game ended while we were looking for it
+            Err(SendError(mut connection)) => {
+                connection.send(&Message::Error("That game is over.".to_string()))
+            }
+        },
+        None => connection.send(&Message::Error(
+            "There is no game to go back to with that token.".to_string(),
+        )),
+    }
+}
+
+/// The games in the lobby, as `GAME` and `LIVE` lines
+fn listings(lobby: &Mutex<Lobby>) -> Vec<Message> {
+    let (mut listings, running_games) = {
+        let lobby = lobby.lock().expect("The lobby lock is never poisoned");
+        let open_games = lobby.open_games.iter().map(|open_game| Message::Game {
+            id: open_game.id,
+            width: open_game.board.width(),
+            height: open_game.board.height(),
+            win_length: open_game.win_length,
+            name: open_game.creator.name.clone(),
+        });
+        (open_games.collect::<Vec<_>>(), lobby.running_games.clone())
+    };
+    // The games are described once the lobby is unlocked, so nobody else has to wait for their audiences
+    listings.extend(running_games.iter().map(|(id, audience)| {
+        let audience = audience
+            .lock()
+            .expect("The audience lock is never poisoned");
+        Message::Live {
+            id: *id,
+            description: format!(
+                "{} against {} on a {}x{} board, {} in a row",
+                audience.seats[0].name,
+                audience.seats[1].name,
+                audience.board.width(),
+                audience.board.height(),
+                audience.win_length
+            ),
+        }
+    }));
+    listings
+}
+
+/// Creates a game, and parks its creator in it until someone else joins. If the creator hangs up before then, the game
+/// is taken out of the lobby again.
+///
+/// With `quick`, the creator asked for a quick match, and is paired with anyone who created a game in the meantime.
+fn open(
+    board: Board,
+    win_length: usize,
+    creator: Seat,
+    mut connection: Connection,
+    lobby: Arc<Mutex<Lobby>>,
+    policy: ReconnectPolicy,
+    quick: bool,
+) -> Result<(), NetworkError> {
+    let id = {
+        let mut lobby = lobby.lock().expect("The lobby lock is never poisoned");
+        lobby.next_id += 1;
+        lobby.next_id
+    };
+    connection.send(&Message::Created(id))?;
+    let watcher = connection.hang_up_watcher()?;
+    {
+        let mut locked_lobby = lobby.lock().expect("The lobby lock is never poisoned");
+        if quick && let Some(open_game) = locked_lobby.take(None) {
+            drop(locked_lobby);
+            return start_game(open_game, creator, connection, lobby, policy);
+        }
+        println!("{} created game {id}.", creator.name);
+        locked_lobby.open_games.push(OpenGame {
+            id,
+            board,
+            win_length,
+            creator,
+            connection,
+        });
+    }
+
+    // The creator has nothing to say until the game starts, so once they do, the game is not ours to look after anymore
+    if watcher.wait()
+        && let Some(open_game) = lobby
+            .lock()
+            .expect("The lobby lock is never poisoned")
+            .take(Some(id))
+    {
+        println!(
+            "{} left game {id} before anyone joined.",
+            open_game.creator.name
+        );
+    }
+    Ok(())
+}
+
+impl Lobby {
+    /// Takes the game with the given id out of the lobby, or the one that has waited the longest if no id is given
+    fn take(&mut self, id: Option<u32>) -> Option<OpenGame> {
+        let position = match id {
+            Some(id) => self
+                .open_games
+                .iter()
+                .position(|open_game| open_game.id == id)?,
+            None if self.open_games.is_empty() => return None,
+            None => 0,
+        };
+        Some(self.open_games.remove(position))
+    }
+
+    fn audience(&self, id: u32) -> Option<Arc<Mutex<Audience>>> {
+        self.running_games
+            .iter()
+            .find(|(running_id, _)| *running_id == id)
+            .map(|(_, audience)| Arc::clone(audience))
+    }
+}
+
+/// Catches a new spectator up on the game so far, and then passes on every move as it happens, until the game is over
+fn watch(audience: Arc<Mutex<Audience>>, mut connection: Connection) -> Result<(), NetworkError> {
+    let (sender, receiver) = mpsc::channel();
+    let (board, win_length, seats, moves) = {
+        let mut audience = audience
+            .lock()
+            .expect("The audience lock is never poisoned");
+        audience.spectators.push(sender);
+        (
+            audience.board.clone(),
+            audience.win_length,
+            audience.seats.clone(),
+            audience.moves.clone(),
+        )
+    };
+    // The messages stop coming once the game is over and the audience is gone
+    drop(audience);
+    network::send_rules_and_players(&mut connection, &board, win_length, &seats)?;
+    connection.send(&Message::Moves(moves))?;
+    for message in receiver {
+        connection.send(&message)?;
+    }
+    Ok(())
+}
+
+impl Audience {
+    /// Keeps track of the moves in the current game, and tells every spectator about them
+    fn tell(&mut self, message: Message) {
+        match message {
+            Message::Move(index) => self.moves.push(index),
+            Message::Restart => self.moves.clear(),
+            _ => {}
+        }
+        // Spectators who have stopped watching are forgotten
+        self.spectators
+            .retain(|spectator| spectator.send(message.clone()).is_ok());
+    }
+}
+
+/// Seats the second player in an open game, and plays it on a thread of its own
+fn start_game(
+    open_game: OpenGame,
+    seat: Seat,
+    mut connection: Connection,
+    lobby: Arc<Mutex<Lobby>>,
+    policy: ReconnectPolicy,
+) -> Result<(), NetworkError> {
+    let OpenGame {
+        id,
+        board,
+        win_length,
+        creator,
+        connection: mut creator_connection,
+    } = open_game;
+    let seats = [creator.clone(), seat.with_symbol_besides(creator.symbol)];
+    let mut rng = Rng::from_seed_or_time(None);
+    let tokens = [
+        reconnect::new_token(&mut rng),
+        reconnect::new_token(&mut rng),
+    ];
+
+    // The creator may have given up waiting, so we only tell the second player the game is on once that worked
+    if network::send_game_start(
+        &mut creator_connection,
+        &board,
+        win_length,
+        &seats,
+        1,
+        &tokens[0],
+    )
+    .is_err()
+    {
+        connection.send(&Message::Error(format!(
+            "{} is not waiting for game {id} anymore.",
+            creator.name
+        )))?;
+        return Ok(());
+    }
+    if let Err(e) =
+        network::send_game_start(&mut connection, &board, win_length, &seats, 2, &tokens[1])
+    {
+        let _ = creator_connection.send(&Message::Exit);
+        return Err(e);
+    }
+    println!(
+        "Game {id} started: {} against {}.",
+        seats[0].name, seats[1].name
+    );
+
+    let audience = Arc::new(Mutex::new(Audience {
+        board: board.clone(),
+        win_length,
+        seats: seats.clone(),
+        moves: Vec::new(),
+        spectators: Vec::new(),
+    }));
+    let (first_sender, first_receiver) = mpsc::channel();
+    let (second_sender, second_receiver) = mpsc::channel();
+    {
+        let mut lobby = lobby.lock().expect("The lobby lock is never poisoned");
+        lobby.running_games.push((id, Arc::clone(&audience)));
+        lobby.sessions.push((tokens[0].clone(), first_sender));
+        lobby.sessions.push((tokens[1].clone(), second_sender));
+    }
+
+    thread::spawn(move || {
+        let [first, second] = seats;
+        let [first_token, second_token] = tokens.clone();
+        let session = |token, receiver| Session {
+            token,
+            rejoin: Rejoin::Lobby(receiver),
+            policy,
+        };
+        let players = PlayerList::new(
+            network::remote_player(
+                &first.name,
+                first.symbol,
+                1,
+                creator_connection,
+                session(first_token, first_receiver),
+            ),
+            network::remote_player(
+                &second.name,
+                second.symbol,
+                2,
+                connection,
+                session(second_token, second_receiver),
+            ),
+        );
+        let mut game = GameState::new(board, GameReferee::new(win_length), players);
+        referee(&mut game, &audience);
+
+        {
+            let mut lobby = lobby.lock().expect("The lobby lock is never poisoned");
+            lobby
+                .running_games
+                .retain(|(running_id, _)| *running_id != id);
+            lobby.sessions.retain(|(token, _)| !tokens.contains(token));
+        }
+        audience
+            .lock()
+            .expect("The audience lock is never poisoned")
+            .tell(Message::Exit);
+        println!("Game {id} is over.");
+    });
+    Ok(())
+}
+
+/// Passes moves between the players until one of them leaves, checking each move on the server's copy of the game
+fn referee(game: &mut GameState, audience: &Mutex<Audience>) {
+    let tell_audience = |message: Message| {
+        audience
+            .lock()
+            .expect("The audience lock is never poisoned")
+            .tell(message);
+    };
+    loop {
+        if game.outcome().is_some() {
+            if !everyone_wants_another_game(game) {
+                return;
+            }
+            game.restart();
+            tell_audience(Message::Restart);
+            continue;
+        }
+        let player = game.current_player();
+        let piece = player.player_piece;
+        match player.controller.handle_input(game) {
+            Ok(InputType::Coord(index)) if game.place_piece(index).is_ok() => {
+                tell_audience(Message::Move(index));
+            }
+            Ok(InputType::Restart) => {
+                game.notify_players_except(piece, GameEvent::Restarted);
+                game.restart();
+                tell_audience(Message::Restart);
+            }
+            _ => {
+                game.notify_players_except(piece, GameEvent::Exited);
+                return;
+            }
+        }
+    }
+}
+
+/// Asks the players whether they want to play again, in the same order as their own copies of the game do
+fn everyone_wants_another_game(game: &GameState) -> bool {
+    for player in game.players().iter() {
+        let again = player.controller.get_yes_no().unwrap_or(false);
+        game.notify_players_except(player.player_piece, GameEvent::Answered(again));
+        if !again {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Starts a server on a free port, and returns its address
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, ReconnectPolicy::default()));
+        address
+    }
+
+    fn open_games(connection: &mut Connection) -> Vec<u32> {
+        let (games, _) = crate::network::lobby::list_games(connection).unwrap();
+        games.iter().map(|game| game.id).collect()
+    }
+
+    #[test]
+    fn a_game_is_withdrawn_when_its_creator_hangs_up() {
+        let address = start_server();
+        let mut ann = network::connect(&address, "Ann", 'x').unwrap();
+        ann.send(&Message::Create {
+            width: 3,
+            height: 3,
+            win_length: 3,
+        })
+        .unwrap();
+        assert_eq!(ann.receive().unwrap(), Message::Created(1));
+        let mut bob = network::connect(&address, "Bob", 'o').unwrap();
+        assert_eq!(open_games(&mut bob), vec![1]);
+
+        drop(ann);
+        for _ in 0..100 {
+            if open_games(&mut bob).is_empty() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("Game 1 is still open after Ann hung up");
+    }
+
+    /// Waits for the game to start, and returns the names in seat order and the seat we got
+    fn wait_for_start(connection: &mut Connection) -> ([String; 2], u8) {
+        network::wait_for_rules(connection).unwrap();
+        match network::receive_players(connection).unwrap() {
+            (seats, Message::Start { seat, .. }) => (seats.map(|(_, seat)| seat.name), seat),
+            (_, message) => panic!("Expected START, got {message}"),
+        }
+    }
+
+    #[test]
+    fn quick_match_pairs_players_up() {
+        let address = start_server();
+        let mut ann = network::connect(&address, "Ann", 'x').unwrap();
+        ann.send(&Message::Quick).unwrap();
+        assert_eq!(ann.receive().unwrap(), Message::Created(1));
+        let mut bob = network::connect(&address, "Bob", 'x').unwrap();
+        bob.send(&Message::Quick).unwrap();
+
+        let names = ["Ann".to_string(), "Bob".to_string()];
+        assert_eq!(wait_for_start(&mut ann), (names.clone(), 1));
+        assert_eq!(wait_for_start(&mut bob), (names, 2));
+
+        // Once they are playing, the game is not open anymore, so the next player gets a new one
+        let mut cid = network::connect(&address, "Cid", 'x').unwrap();
+        assert!(open_games(&mut cid).is_empty());
+        cid.send(&Message::Quick).unwrap();
+        assert_eq!(cid.receive().unwrap(), Message::Created(2));
+    }
+
+    #[test]
+    fn spectators_follow_the_game() {
+        let address = start_server();
+        let mut ann = network::connect(&address, "Ann", 'x').unwrap();
+        ann.send(&Message::Create {
+            width: 3,
+            height: 3,
+            win_length: 3,
+        })
+        .unwrap();
+        assert_eq!(ann.receive().unwrap(), Message::Created(1));
+        let mut bob = network::connect(&address, "Bob", 'o').unwrap();
+        bob.send(&Message::Enter(1)).unwrap();
+        wait_for_start(&mut ann);
+        wait_for_start(&mut bob);
+        ann.send(&Message::Move(4)).unwrap();
+        assert_eq!(bob.receive().unwrap(), Message::Move(4));
+
+        // A spectator who comes in late catches up on the moves so far
+        let mut cid = network::connect(&address, "Cid", 'x').unwrap();
+        cid.send(&Message::Watch(1)).unwrap();
+        network::wait_for_rules(&mut cid).unwrap();
+        let (seats, catch_up) = network::receive_players(&mut cid).unwrap();
+        assert_eq!(seats.map(|(_, seat)| seat.name), ["Ann", "Bob"]);
+        assert_eq!(catch_up, Message::Moves(vec![4]));
+
+        bob.send(&Message::Move(0)).unwrap();
+        assert_eq!(ann.receive().unwrap(), Message::Move(0));
+        assert_eq!(cid.receive().unwrap(), Message::Move(0));
+
+        // A move the server's copy of the game refuses ends the game, and is never passed on
+        ann.send(&Message::Move(0)).unwrap();
+        assert_eq!(bob.receive().unwrap(), Message::Exit);
+        assert_eq!(cid.receive().unwrap(), Message::Exit);
+    }
+}
@@ -0,0 +1,619 @@
+//! A full-screen terminal UI for the players at this computer. They move a highlighted cursor around the board with
+//! the arrow keys or hjkl, and press Enter to place their piece.
+//!
+//! The screen is only taken over while it's the player's turn. Everything else, like the AI thinking or who won, is
+//! printed line by line as usual, and shows once the full screen is left.
+
+use crate::GameState;
+use crate::board::{Board, CellState};
+use crate::controller::{InputController, InputError, InputType, PlayerController};
+use crate::coordinates;
+use crate::gamestate::{Move, Score};
+use crate::player::base_player::{LocalPlayer, PlayerPiece};
+use crate::render::Colour;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+
+/// Where the board is drawn, counting from the top left of the screen. The column letters go above it, and the row
+/// numbers to the left of it.
+const BOARD_TOP: u16 = 3;
+const BOARD_LEFT: u16 = 4;
+/// The space between the board and the move list
+const PANEL_GAP: u16 = 4;
+
+pub struct TuiPlayer {
+    keys: KeyBindings,
+    /// The colours of the players' symbols, in the order the players move
+    colours: [Colour; 2],
+    /// The cell the cursor is on. It stays where the player left it from one turn to the next.
+    cursor: Cell<Option<usize>>,
+    /// What the screen shows, taken from the game each time it's this player's turn
+    view: RefCell<Option<View>>,
+}
+
+/// The letter keys that do things. The arrow keys, Enter and Esc work as well, whatever these are set to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBindings {
+    pub left: char,
+    pub down: char,
+    pub up: char,
+    pub right: char,
+    pub place: char,
+    pub undo: char,
+    pub redo: char,
+    pub restart: char,
+    pub quit: char,
+}
+
+/// What a key press asks for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Action {
+    Left,
+    Down,
+    Up,
+    Right,
+    Place,
+    Undo,
+    Redo,
+    Restart,
+    Quit,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left: 'h',
+            down: 'j',
+            up: 'k',
+            right: 'l',
+            place: ' ',
+            undo: 'u',
+            redo: 'r',
+            restart: 'n',
+            quit: 'q',
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every binding, along with what it's called in the config file
+    pub fn named(&self) -> [(&'static str, char); 9] {
+        [
+            ("left", self.left),
+            ("down", self.down),
+            ("up", self.up),
+            ("right", self.right),
+            ("place", self.place),
+            ("undo", self.undo),
+            ("redo", self.redo),
+            ("restart", self.restart),
+            ("quit", self.quit),
+        ]
+    }
+
+    /// The first two bindings that share a key, as positions in `named`. Only one of them could ever be used.
+    pub fn clash(&self) -> Option<(usize, usize)> {
+        let named = self.named();
+        (0..named.len()).find_map(|index| {
+            (0..index)
+                .find(|&other| named[other].1 == named[index].1)
+                .map(|other| (other, index))
+        })
+    }
+
+    fn action(&self, key: KeyEvent) -> Option<Action> {
+        let action = match key.code {
+            KeyCode::Left => Action::Left,
+            KeyCode::Down => Action::Down,
+            KeyCode::Up => Action::Up,
+            KeyCode::Right => Action::Right,
+            KeyCode::Enter => Action::Place,
+            KeyCode::Esc => Action::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+            KeyCode::Char(c) if c == self.left => Action::Left,
+            KeyCode::Char(c) if c == self.down => Action::Down,
+            KeyCode::Char(c) if c == self.up => Action::Up,
+            KeyCode::Char(c) if c == self.right => Action::Right,
+            KeyCode::Char(c) if c == self.place => Action::Place,
+            KeyCode::Char(c) if c == self.undo => Action::Undo,
+            KeyCode::Char(c) if c == self.redo => Action::Redo,
+            KeyCode::Char(c) if c == self.restart => Action::Restart,
+            KeyCode::Char(c) if c == self.quit => Action::Quit,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// The line at the bottom of the screen that says which key does what
+    fn help(&self) -> String {
+        let key = |c: char| {
+            if c == ' ' {
+                "Space".to_string()
+            } else {
+                c.to_string()
+            }
+        };
+        format!(
+            "Arrows/{}{}{}{}: move  Enter/{}: place  {}: undo  {}: redo  {}: restart  {}: quit",
+            key(self.left),
+            key(self.down),
+            key(self.up),
+            key(self.right),
+            key(self.place),
+            key(self.undo),
+            key(self.redo),
+            key(self.restart),
+            key(self.quit)
+        )
+    }
+}
+
+/// Everything about the game that is drawn on the screen
+struct View {
+    board: Board,
+    win_length: usize,
+    /// In the order the players move
+    players: Vec<Seat>,
+    /// The position in `players` of whoever is to move
+    to_move: usize,
+    history: Vec<Move>,
+    score: Score,
+    can_undo: bool,
+    can_redo: bool,
+    networked: bool,
+}
+
+struct Seat {
+    name: String,
+    symbol: char,
+    piece: PlayerPiece,
+}
+
+/// Raw mode and the alternate screen, for as long as this is around. Dropping it gives the terminal back the way it was.
+struct FullScreen;
+
+impl FullScreen {
+    fn enter() -> io::Result<FullScreen> {
+        terminal::enable_raw_mode()?;
+        let screen = FullScreen;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for FullScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl TuiPlayer {
+    pub fn new(keys: KeyBindings, colours: [Colour; 2]) -> TuiPlayer {
+        TuiPlayer {
+            keys,
+            colours,
+            cursor: Cell::new(None),
+            view: RefCell::new(None),
+        }
+    }
+
+    /// Shows the game until the player picks a cell or a command, and returns it the way it would be typed
+    fn run(&self, view: &View) -> io::Result<String> {
+        let _screen = FullScreen::enter()?;
+        let mut stdout = io::stdout();
+        let mut cursor = self
+            .cursor
+            .get()
+            .filter(|&index| index < view.board.len())
+            .unwrap_or(view.board.len() / 2);
+        let mut message: Option<String> = None;
+        loop {
+            self.cursor.set(Some(cursor));
+            self.draw(&mut stdout, view, cursor, message.as_deref())?;
+            // Anything else, like the terminal being resized, just redraws the screen
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            message = None;
+            let (row, col) = (cursor / view.board.width(), cursor % view.board.width());
+            match self.keys.action(key) {
+                Some(Action::Left) if col > 0 => cursor -= 1,
+                Some(Action::Right) if col + 1 < view.board.width() => cursor += 1,
+                Some(Action::Up) if row > 0 => cursor -= view.board.width(),
+                Some(Action::Down) if row + 1 < view.board.height() => {
+                    cursor += view.board.width();
+                }
+                Some(Action::Place) => match view.board.is_valid_move(cursor) {
+                    Ok(()) => return Ok(cursor.to_string()),
+                    Err(_) => {
+                        let cell = coordinates::cell_label(&view.board, cursor);
+                        message = Some(format!("{cell} is already taken."));
+                    }
+                },
+                Some(Action::Undo | Action::Redo) if view.networked => {
+                    message = Some("Moves can't be taken back in a network game.".to_string());
+                }
+                Some(Action::Undo) if !view.can_undo => {
+                    message = Some("There is nothing to undo.".to_string());
+                }
+                Some(Action::Redo) if !view.can_redo => {
+                    message = Some("There is nothing to redo.".to_string());
+                }
+                Some(Action::Undo) => return Ok("undo".to_string()),
+                Some(Action::Redo) => return Ok("redo".to_string()),
+                Some(Action::Restart) => return Ok("restart".to_string()),
+                Some(Action::Quit) => return Ok("exit".to_string()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl View {
+    fn new(game_state: &GameState) -> View {
+        let players = game_state
+            .players()
+            .iter()
+            .map(|player| Seat {
+                name: player.name.clone(),
+                symbol: player.symbol,
+                piece: player.player_piece,
+            })
+            .collect::<Vec<_>>();
+        let to_move = players
+            .iter()
+            .position(|seat| seat.piece == game_state.piece_to_move())
+            .unwrap_or(0);
+        View {
+            board: game_state.board().clone(),
+            win_length: game_state.referee().win_length(),
+            players,
+            to_move,
+            history: game_state.history().to_vec(),
+            score: game_state.score(),
+            can_undo: game_state.can_undo(),
+            can_redo: game_state.can_redo(),
+            networked: game_state.players().iter().any(|player| player.is_remote()),
+        }
+    }
+
+    /// The position in `players` of whoever plays with `piece`
+    fn seat_of(&self, piece: PlayerPiece) -> usize {
+        self.players
+            .iter()
+            .position(|seat| seat.piece == piece)
+            .unwrap_or(0)
+    }
+}
+
+impl TuiPlayer {
+    /// Draws the whole screen: a title, the board with the cursor on it, the move list beside it, and a status bar at
+    /// the bottom
+    fn draw(
+        &self,
+        stdout: &mut io::Stdout,
+        view: &View,
+        cursor: usize,
+        message: Option<&str>,
+    ) -> io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        queue!(
+            stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(1, 0)
+        )?;
+        queue!(
+            stdout,
+            SetAttribute(Attribute::Bold),
+            Print(format!("Tic tac toe, {} in a row", view.win_length)),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        let board = &view.board;
+        for col in 0..board.width() {
+            queue!(
+                stdout,
+                cursor::MoveTo(BOARD_LEFT + 4 * col as u16 + 1, BOARD_TOP - 1),
+                Print(coordinates::column_label(col))
+            )?;
+        }
+        for (row, cells) in board.get_all_rows().iter().enumerate() {
+            let y = BOARD_TOP + 2 * row as u16;
+            if row > 0 {
+                let line = vec!["───"; board.width()].join("┼");
+                queue!(stdout, cursor::MoveTo(BOARD_LEFT, y - 1), Print(line))?;
+            }
+            queue!(
+                stdout,
+                cursor::MoveTo(1, y),
+                Print(format!("{:>2}", row + 1)),
+                cursor::MoveTo(BOARD_LEFT, y)
+            )?;
+            for (col, cell) in cells.iter().enumerate() {
+                if col > 0 {
+                    queue!(stdout, Print("│"))?;
+                }
+                let highlighted = row * board.width() + col == cursor;
+                if highlighted {
+                    queue!(stdout, SetAttribute(Attribute::Reverse))?;
+                }
+                match cell {
+                    CellState::Empty => queue!(stdout, Print("   "))?,
+                    CellState::Player(piece) => {
+                        let seat = view.seat_of(*piece);
+                        queue!(
+                            stdout,
+                            Print(" "),
+                            SetForegroundColor(terminal_colour(
+                                self.colours[seat % self.colours.len()]
+                            )),
+                            SetAttribute(Attribute::Bold),
+                            Print(view.players[seat].symbol),
+                            SetAttribute(Attribute::NormalIntensity),
+                            SetForegroundColor(Color::Reset),
+                            Print(" ")
+                        )?;
+                    }
+                }
+                if highlighted {
+                    queue!(stdout, SetAttribute(Attribute::NoReverse))?;
+                }
+            }
+        }
+
+        // The move list only has room for the latest moves on a short screen
+        let panel_left = BOARD_LEFT + 4 * board.width() as u16 - 1 + PANEL_GAP;
+        let panel_rows = rows.saturating_sub(BOARD_TOP + 4) as usize;
+        queue!(
+            stdout,
+            cursor::MoveTo(panel_left, BOARD_TOP),
+            SetAttribute(Attribute::Underlined),
+            Print("Moves"),
+            SetAttribute(Attribute::NoUnderline)
+        )?;
+        let skipped = view.history.len().saturating_sub(panel_rows);
+        for (line, (number, played)) in view.history.iter().enumerate().skip(skipped).enumerate() {
+            let seat = &view.players[view.seat_of(played.piece)];
+            queue!(
+                stdout,
+                cursor::MoveTo(panel_left, BOARD_TOP + 1 + line as u16),
+                Print(format!(
+                    "{:>3}. {} {}",
+                    number + 1,
+                    seat.symbol,
+                    coordinates::cell_label(board, played.index)
+                ))
+            )?;
+        }
+
+        if let Some(message) = message {
+            queue!(
+                stdout,
+                cursor::MoveTo(BOARD_LEFT, rows.saturating_sub(3)),
+                Print(message)
+            )?;
+        }
+        let to_move = &view.players[view.to_move];
+        let wins = view
+            .players
+            .iter()
+            .zip(view.score.wins)
+            .map(|(seat, wins)| format!("{} {wins}", seat.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = format!(
+            " {} ({}) to move    Score: {wins}, draws {}",
+            to_move.name, to_move.symbol, view.score.draws
+        );
+        queue!(
+            stdout,
+            cursor::MoveTo(0, rows.saturating_sub(2)),
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{status:<width$}", width = columns as usize)),
+            SetAttribute(Attribute::NoReverse),
+            cursor::MoveTo(1, rows.saturating_sub(1)),
+            Print(self.keys.help())
+        )?;
+        stdout.flush()
+    }
+}
+
+/// The terminal's version of `colour`
+fn terminal_colour(colour: Colour) -> Color {
+    match colour {
+        Colour::Red => Color::Red,
+        Colour::Green => Color::Green,
+        Colour::Yellow => Color::Yellow,
+        Colour::Blue => Color::Blue,
+        Colour::Magenta => Color::Magenta,
+        Colour::Cyan => Color::Cyan,
+    }
+}
+
+/// Waits for a single key press, without taking over the screen
+fn read_key() -> io::Result<KeyEvent> {
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(key),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    key
+}
+
+impl InputController for TuiPlayer {
+    fn get_raw_input(&self) -> String {
+        let view = self.view.borrow();
+        let view = view
+            .as_ref()
+            .expect("The game is looked at before asking for input");
+        self.run(view).unwrap_or_else(|e| {
+            println!("The terminal UI stopped working: {e}");
+            "exit".to_string()
+        })
+    }
+
+    fn parse_input(&self, input: &str, board_info: &Board) -> Result<InputType, InputError> {
+        // The screen hands back what a player would have typed, so it means the same thing
+        LocalPlayer {}.parse_input(input, board_info)
+    }
+}
+
+impl PlayerController for TuiPlayer {
+    fn handle_input(&self, game_state: &GameState) -> Result<InputType, InputError> {
+        *self.view.borrow_mut() = Some(View::new(game_state));
+        self.parse_input(&self.get_raw_input(), game_state.board())
+    }
+
+    fn get_yes_no(&self) -> Result<bool, InputError> {
+        match read_key() {
+            Ok(KeyEvent {
+                code: KeyCode::Char('y' | 'Y'),
+                ..
+            }) => Ok(true),
+            Ok(KeyEvent {
+                code: KeyCode::Char('n' | 'N' | 'q') | KeyCode::Esc,
+                ..
+            }) => Ok(false),
+            Ok(_) => Err(InputError::InvalidCommand),
+            // Without a working terminal, there is no playing another game
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn ctrl_c_always_quits() {
+        let keys = KeyBindings::default();
+        assert_eq!(
+            keys.action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(keys.action(press(KeyCode::Char('c'))), None);
+
+        // Even when plain c is bound to something else
+        let keys = KeyBindings {
+            restart: 'c',
+            ..KeyBindings::default()
+        };
+        assert_eq!(
+            keys.action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keys.action(press(KeyCode::Char('c'))),
+            Some(Action::Restart)
+        );
+    }
+
+    #[test]
+    fn arrows_enter_and_esc_work_whatever_the_bindings() {
+        let custom = KeyBindings {
+            left: 'a',
+            down: 's',
+            up: 'w',
+            right: 'd',
+            ..KeyBindings::default()
+        };
+        for keys in [KeyBindings::default(), custom] {
+            for (code, action) in [
+                (KeyCode::Left, Action::Left),
+                (KeyCode::Down, Action::Down),
+                (KeyCode::Up, Action::Up),
+                (KeyCode::Right, Action::Right),
+                (KeyCode::Enter, Action::Place),
+                (KeyCode::Esc, Action::Quit),
+            ] {
+                assert_eq!(keys.action(press(code)), Some(action), "{code:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn letters_follow_the_bindings() {
+        let defaults = KeyBindings::default();
+        for (c, action) in [
+            ('h', Action::Left),
+            ('j', Action::Down),
+            ('k', Action::Up),
+            ('l', Action::Right),
+            (' ', Action::Place),
+            ('u', Action::Undo),
+            ('r', Action::Redo),
+            ('n', Action::Restart),
+            ('q', Action::Quit),
+        ] {
+            assert_eq!(
+                defaults.action(press(KeyCode::Char(c))),
+                Some(action),
+                "{c:?}"
+            );
+        }
+
+        let custom = KeyBindings {
+            left: 'a',
+            place: 'p',
+            undo: 'z',
+            ..KeyBindings::default()
+        };
+        assert_eq!(custom.action(press(KeyCode::Char('a'))), Some(Action::Left));
+        assert_eq!(
+            custom.action(press(KeyCode::Char('p'))),
+            Some(Action::Place)
+        );
+        assert_eq!(custom.action(press(KeyCode::Char('z'))), Some(Action::Undo));
+        // The keys they replaced don't do anything any more
+        for c in ['h', ' ', 'u'] {
+            assert_eq!(custom.action(press(KeyCode::Char(c))), None, "{c:?}");
+        }
+        assert_eq!(custom.action(press(KeyCode::Char('j'))), Some(Action::Down));
+        assert_eq!(custom.action(press(KeyCode::Tab)), None);
+    }
+
+    #[test]
+    fn help_shows_the_bindings() {
+        assert_eq!(
+            KeyBindings::default().help(),
+            "Arrows/hjkl: move  Enter/Space: place  u: undo  r: redo  n: restart  q: quit"
+        );
+        let custom = KeyBindings {
+            place: 'p',
+            quit: 'x',
+            ..KeyBindings::default()
+        };
+        assert_eq!(
+            custom.help(),
+            "Arrows/hjkl: move  Enter/p: place  u: undo  r: redo  n: restart  x: quit"
+        );
+    }
+
+    #[test]
+    fn keys_bound_twice_clash() {
+        assert_eq!(KeyBindings::default().clash(), None);
+        let keys = KeyBindings {
+            quit: 'u',
+            ..KeyBindings::default()
+        };
+        // undo and quit
+        assert_eq!(keys.clash(), Some((5, 8)));
+    }
+}
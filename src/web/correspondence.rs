@@ -0,0 +1,333 @@
+use crate::GameState;
+use crate::gamestate::GameReferee;
+use crate::network;
+use crate::network::reconnect;
+use crate::player::base_player::{Player, PlayerPiece};
+use crate::player::playerlist::PlayerList;
+use crate::rng::Rng;
+use crate::web::game::BrowserPlayer;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A game played one move at a time, maybe days apart, through the REST API. It is stored just like this, as JSON.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Correspondence {
+    pub id: u32,
+    pub width: usize,
+    pub height: usize,
+    pub win_length: usize,
+    /// In seat order
+    pub players: [Correspondent; 2],
+    pub moves: Vec<PlayedMove>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Correspondent {
+    pub name: String,
+    pub symbol: char,
+    /// The secret a player sends along with their moves, to prove who they are
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct PlayedMove {
+    pub seat: u8,
+    pub index: usize,
+    /// Seconds since the Unix epoch
+    pub played_at: u64,
+}
+
+/// Why a move was not made
+pub enum MoveError {
+    /// The token does not belong to either player
+    NotAPlayer,
+    /// It's the other player's turn, or the game is over
+    OutOfTurn(String),
+    InvalidMove(String),
+    Io(io::Error),
+}
+
+/// The correspondence games, kept in a directory with one file per game so they survive restarts
+pub struct Archive {
+    dir: PathBuf,
+    games: Vec<Correspondence>,
+    rng: Rng,
+}
+
+impl Correspondence {
+    /// Sets the game up again from its moves, so the usual rules can have their say about it
+    pub fn game_state(&self) -> GameState {
+        self.try_game_state()
+            .expect("Only games with legal rules and moves are ever stored")
+    }
+
+    /// Sets the game up again, or returns None if its rules or moves are not legal, e.g. in a file someone edited
+    fn try_game_state(&self) -> Option<GameState> {
+        let (board, win_length) = network::check_rules(self.width, self.height, self.win_length)?;
+        let [first, second] = &self.players;
+        let player = |correspondent: &Correspondent, seat| {
+            Player::remote(
+                &correspondent.name,
+                correspondent.symbol,
+                PlayerPiece::new(seat),
+                Box::new(BrowserPlayer {}),
+            )
+        };
+        let players = PlayerList::new(player(first, 1), player(second, 2));
+        let mut game_state = GameState::new(board, GameReferee::new(win_length), players);
+        let moves: Vec<usize> = self.moves.iter().map(|played| played.index).collect();
+        game_state.replay(&moves).ok()?;
+        Some(game_state)
+    }
+
+    fn seat_of(&self, token: &str) -> Option<u8> {
+        self.players
+            .iter()
+            .position(|correspondent| correspondent.token == token)
+            .map(|position| position as u8 + 1)
+    }
+}
+
+impl Archive {
+    /// Opens the archive in `dir`, creating the directory if it isn't there yet.
+    ///
+    /// A file we can't make sense of is moved aside, with `.broken` added to its name, so the other games can still be
+    /// played and a new game doesn't end up overwriting it.
+    pub fn open(dir: &Path) -> io::Result<Archive> {
+        fs::create_dir_all(dir)?;
+        let mut games = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match read_game(&path) {
+                Ok(game) => games.push(game),
+                Err(reason) => {
+                    let mut broken = path.clone().into_os_string();
+                    broken.push(".broken");
+                    fs::rename(&path, &broken)?;
+                    println!(
+                        "Moved {} aside to {}, since {reason}.",
+                        path.display(),
+                        Path::new(&broken).display()
+                    );
+                }
+            }
+        }
+        games.sort_by_key(|game| game.id);
+        Ok(Archive {
+            dir: dir.to_path_buf(),
+            games,
+            rng: Rng::from_seed_or_time(None),
+        })
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Correspondence> {
+        self.games.iter().find(|game| game.id == id)
+    }
+
+    /// Starts a game between two players, given as their names and symbols. The rules must have been checked already.
+    pub fn create(
+        &mut self,
+        width: usize,
+        height: usize,
+        win_length: usize,
+        players: [(String, char); 2],
+    ) -> io::Result<&Correspondence> {
+        let id = self.games.last().map_or(1, |game| game.id + 1);
+        let players = players.map(|(name, symbol)| Correspondent {
+            name,
+            symbol,
+            token: reconnect::new_token(&mut self.rng),
+        });
+        let game = Correspondence {
+            id,
+            width,
+            height,
+            win_length,
+            players,
+            moves: Vec::new(),
+        };
+        self.save(&game)?;
+        self.games.push(game);
+        Ok(self.games.last().expect("The game was just added"))
+    }
+
+    /// Plays a move for the player with `token`, and saves the game before anyone hears about it
+    pub fn play(
+        &mut self,
+        id: u32,
+        token: &str,
+        index: usize,
+    ) -> Result<&Correspondence, MoveError> {
+        let position = self
+            .games
+            .iter()
+            .position(|game| game.id == id)
+            .expect("Only games that exist are played in");
+        let mut game = self.games[position].clone();
+        let seat = game.seat_of(token).ok_or(MoveError::NotAPlayer)?;
+
+        let mut game_state = game.game_state();
+        if game_state.outcome().is_some() {
+            return Err(MoveError::OutOfTurn("The game is over.".to_string()));
+        }
+        if *game_state.piece_to_move() != seat {
+            return Err(MoveError::OutOfTurn("It's not your turn.".to_string()));
+        }
+        game_state
+            .board()
+            .is_valid_move(index)
+            .map_err(|e| MoveError::InvalidMove(e.to_string()))?;
+        game_state
+            .place_piece(index)
+            .expect("The move was checked before it was made");
+
+        let played_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        game.moves.push(PlayedMove {
+            seat,
+            index,
+            played_at,
+        });
+        self.save(&game).map_err(MoveError::Io)?;
+        self.games[position] = game;
+        Ok(&self.games[position])
+    }
+
+    /// Writes the game to a file of its own. It goes through a temporary file, so a crash never leaves half a game behind.
+    fn save(&self, game: &Correspondence) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(game).expect("Games can always be written as JSON");
+        let path = self.dir.join(format!("{}.json", game.id));
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, &path)
+    }
+}
+
+/// Reads a stored game, or says what is wrong with it
+fn read_game(path: &Path) -> Result<Correspondence, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("it could not be read: {e}"))?;
+    let game: Correspondence =
+        serde_json::from_str(&json).map_err(|e| format!("it is not a game we can read: {e}"))?;
+    if game.try_game_state().is_none() {
+        return Err("it is not a legal game".to_string());
+    }
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /// An empty directory of its own for each test, which is removed again when the test is done
+    struct GamesDir(PathBuf);
+
+    impl GamesDir {
+        fn new(name: &str) -> GamesDir {
+            let dir = env::temp_dir().join(format!("tictactoe-archive-{}-{name}", process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            GamesDir(dir)
+        }
+    }
+
+    impl Drop for GamesDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Creates a 3x3 game between Ann and Bob, and returns its id and their tokens
+    fn create(archive: &mut Archive) -> (u32, [String; 2]) {
+        let players = [("Ann".to_string(), 'x'), ("Bob".to_string(), 'y')];
+        let game = archive.create(3, 3, 3, players).unwrap();
+        (game.id, game.players.clone().map(|player| player.token))
+    }
+
+    #[test]
+    fn games_survive_reopening_the_archive() {
+        let dir = GamesDir::new("reopen");
+        let mut archive = Archive::open(&dir.0).unwrap();
+        let (id, [ann, bob]) = create(&mut archive);
+        assert!(archive.play(id, &ann, 4).is_ok());
+        assert!(archive.play(id, &bob, 0).is_ok());
+        drop(archive);
+
+        let mut archive = Archive::open(&dir.0).unwrap();
+        let game = archive.get(id).unwrap();
+        assert_eq!(game.game_state().moves(), vec![4, 0]);
+        assert_eq!(
+            game.players.clone().map(|player| player.token),
+            [ann.clone(), bob]
+        );
+        assert!(archive.play(id, &ann, 8).is_ok());
+        assert_eq!(create(&mut archive).0, id + 1);
+    }
+
+    #[test]
+    fn moves_are_checked() {
+        let dir = GamesDir::new("checks");
+        let mut archive = Archive::open(&dir.0).unwrap();
+        let (id, [ann, bob]) = create(&mut archive);
+        assert!(matches!(
+            archive.play(id, "nobody", 4),
+            Err(MoveError::NotAPlayer)
+        ));
+        assert!(matches!(
+            archive.play(id, &bob, 4),
+            Err(MoveError::OutOfTurn(reason)) if reason == "It's not your turn."
+        ));
+        assert!(archive.play(id, &ann, 4).is_ok());
+        assert!(matches!(
+            archive.play(id, &bob, 4),
+            Err(MoveError::InvalidMove(_))
+        ));
+        assert!(matches!(
+            archive.play(id, &bob, 9),
+            Err(MoveError::InvalidMove(_))
+        ));
+
+        for (token, index) in [(&bob, 0), (&ann, 3), (&bob, 1), (&ann, 5)] {
+            assert!(archive.play(id, token, index).is_ok());
+        }
+        assert!(matches!(
+            archive.play(id, &bob, 2),
+            Err(MoveError::OutOfTurn(reason)) if reason == "The game is over."
+        ));
+        // Nothing that was turned down made it into the file
+        let archive = Archive::open(&dir.0).unwrap();
+        assert_eq!(archive.get(id).unwrap().moves.len(), 5);
+    }
+
+    #[test]
+    fn broken_files_are_moved_aside() {
+        let dir = GamesDir::new("broken");
+        let mut archive = Archive::open(&dir.0).unwrap();
+        let (id, [ann, _]) = create(&mut archive);
+        archive.play(id, &ann, 4).ok();
+        let mut illegal = archive.get(id).unwrap().clone();
+        illegal.id = 3;
+        illegal.moves[0].index = 9;
+        fs::write(dir.0.join("2.json"), "not a game").unwrap();
+        fs::write(
+            dir.0.join("3.json"),
+            serde_json::to_string(&illegal).unwrap(),
+        )
+        .unwrap();
+
+        let archive = Archive::open(&dir.0).unwrap();
+        assert!(archive.get(id).is_some());
+        assert!(archive.get(2).is_none() && archive.get(3).is_none());
+        for name in ["2.json", "3.json"] {
+            assert!(!dir.0.join(name).exists());
+            assert!(dir.0.join(format!("{name}.broken")).exists());
+        }
+    }
+}
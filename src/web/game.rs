@@ -0,0 +1,295 @@
+use crate::GameState;
+use crate::board::Board;
+use crate::controller::*;
+use crate::gamestate::GameReferee;
+use crate::player::base_player::{Player, PlayerPiece};
+use crate::player::playerlist::PlayerList;
+use crate::selfplay::Contender;
+use crate::web::json::{GameView, ServerMessage};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The games being played in browsers. Each game runs on a thread of its own, and is reached through a channel.
+#[derive(Default)]
+pub struct Games {
+    next_game_id: u32,
+    next_client_id: u32,
+    running: Vec<(u32, Sender<Command>)>,
+}
+
+/// Something a browser asks of the game it is in
+pub enum Command {
+    /// Takes the free seat in the game if `play` is set, or just watches it otherwise
+    Enter {
+        client: Client,
+        play: bool,
+    },
+    Input {
+        client_id: u32,
+        input: InputType,
+    },
+    /// The browser closed the connection, or went to another game
+    Leave {
+        client_id: u32,
+    },
+}
+
+/// A browser connected to a game, and where to send the messages meant for it
+pub struct Client {
+    pub id: u32,
+    pub outbox: Sender<ServerMessage>,
+}
+
+/// A player in a browser. Their moves arrive as commands or requests, so the game never has to ask them for input.
+pub struct BrowserPlayer {}
+
+impl PlayerController for BrowserPlayer {
+    fn handle_input(&self, _gamestate: &GameState) -> Result<InputType, InputError> {
+        Ok(InputType::Exit)
+    }
+
+    fn get_yes_no(&self) -> Result<bool, InputError> {
+        Ok(false)
+    }
+}
+
+impl Games {
+    pub fn new_client_id(&mut self) -> u32 {
+        self.next_client_id += 1;
+        self.next_client_id
+    }
+
+    /// Finds a running game, to send commands to
+    pub fn find(&self, id: u32) -> Option<Sender<Command>> {
+        self.running
+            .iter()
+            .find(|(running_id, _)| *running_id == id)
+            .map(|(_, commands)| commands.clone())
+    }
+}
+
+/// Starts a game on a thread of its own. Without an `opponent`, the second seat waits for another browser.
+pub fn create(
+    games: &Arc<Mutex<Games>>,
+    board: Board,
+    win_length: usize,
+    opponent: Option<Contender>,
+) -> (u32, Sender<Command>) {
+    let (sender, commands) = mpsc::channel();
+    let id = {
+        let mut games = games.lock().expect("The games lock is never poisoned");
+        games.next_game_id += 1;
+        let id = games.next_game_id;
+        games.running.push((id, sender.clone()));
+        id
+    };
+
+    let games = Arc::clone(games);
+    thread::spawn(move || {
+        let browser = |name: &str, symbol, seat| {
+            Player::remote(
+                name,
+                symbol,
+                PlayerPiece::new(seat),
+                Box::new(BrowserPlayer {}),
+            )
+        };
+        let second = match &opponent {
+            Some(contender) => Player::ai(
+                &format!("AI ({})", contender.name),
+                'y',
+                PlayerPiece::new(2),
+                contender.strategy,
+            ),
+            None => browser("Player 2", 'y', 2),
+        };
+        let players = PlayerList::new(browser("Player 1", 'x', 1), second);
+        let mut web_game = WebGame {
+            id,
+            game: GameState::new(board, GameReferee::new(win_length), players),
+            clients: Vec::new(),
+            open: opponent.is_none(),
+        };
+        web_game.run(commands);
+
+        games
+            .lock()
+            .expect("The games lock is never poisoned")
+            .running
+            .retain(|(running_id, _)| *running_id != id);
+    });
+    (id, sender)
+}
+
+/// A game played in browsers, along with everyone looking at it
+struct WebGame {
+    id: u32,
+    game: GameState,
+    /// The browsers in the game, and the seat each of them plays, if any
+    clients: Vec<(Client, Option<u8>)>,
+    /// Whether the second seat is still waiting for a browser
+    open: bool,
+}
+
+impl WebGame {
+    /// Handles commands until the game is over for good
+    fn run(&mut self, commands: Receiver<Command>) {
+        while let Ok(command) = commands.recv() {
+            let keep_going = match command {
+                Command::Enter { client, play } => {
+                    self.enter(client, play);
+                    true
+                }
+                Command::Input { client_id, input } => self.play(client_id, input),
+                Command::Leave { client_id } => self.leave(client_id),
+            };
+            if !keep_going {
+                return;
+            }
+        }
+    }
+
+    fn enter(&mut self, client: Client, play: bool) {
+        let seat = match (play, self.clients.iter().any(|(_, seat)| *seat == Some(1))) {
+            (false, _) => None,
+            // The creator takes the first seat, and whoever comes after them the second
+            (true, false) => Some(1),
+            (true, true) if self.open => {
+                self.open = false;
+                Some(2)
+            }
+            (true, true) => {
+                let _ = client.outbox.send(ServerMessage::Error {
+                    message: format!("Game {} already has two players.", self.id),
+                });
+                return;
+            }
+        };
+        let _ = client.outbox.send(ServerMessage::Entered {
+            game: self.id,
+            seat,
+        });
+        self.clients.push((client, seat));
+        self.broadcast_state();
+    }
+
+    /// Applies a browser's input, and lets the AI answer it. Returns false once the game is over for good.
+    fn play(&mut self, client_id: u32, input: InputType) -> bool {
+        let Some(seat) = self.seat_of(client_id) else {
+            self.tell(client_id, "You are only watching this game.");
+            return true;
+        };
+        match input {
+            InputType::Coord(index) => {
+                if let Err(reason) = self.check_move(seat, index) {
+                    self.tell(client_id, &reason);
+                    return true;
+                }
+                self.game
+                    .place_piece(index)
+                    .expect("The move was checked before it was made");
+                self.let_ai_move();
+            }
+            InputType::Restart => self.game.restart(),
+            InputType::Exit => {
+                self.end(seat);
+                return false;
+            }
+            InputType::Help
+            | InputType::Forfeit
+            | InputType::Undo
+            | InputType::Redo
+            | InputType::Save(_)
+            | InputType::Load(_)
+            | InputType::Resync(_) => return true,
+        }
+        self.broadcast_state();
+        true
+    }
+
+    fn check_move(&self, seat: u8, index: usize) -> Result<(), String> {
+        if self.open {
+            return Err(format!("Nobody has joined game {} yet.", self.id));
+        }
+        if self.game.outcome().is_some() {
+            return Err("The game is over. Restart to play again.".to_string());
+        }
+        if *self.game.piece_to_move() != seat {
+            return Err("It's not your turn.".to_string());
+        }
+        self.game
+            .board()
+            .is_valid_move(index)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Lets the AI make its move, if it is its turn
+    fn let_ai_move(&mut self) {
+        if self.game.outcome().is_some() || !self.game.current_player().is_ai() {
+            return;
+        }
+        let input = self
+            .game
+            .current_player()
+            .controller
+            .handle_input(&self.game);
+        if let Ok(InputType::Coord(index)) = input {
+            let _ = self.game.place_piece(index);
+        }
+    }
+
+    /// Returns false if a player left, which ends the game for everyone else
+    fn leave(&mut self, client_id: u32) -> bool {
+        let seat = self.seat_of(client_id);
+        self.clients.retain(|(client, _)| client.id != client_id);
+        match seat {
+            Some(seat) => {
+                self.end(seat);
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn end(&mut self, seat: u8) {
+        let name = self
+            .game
+            .players()
+            .iter()
+            .find(|player| *player.player_piece == seat)
+            .map_or("A player", |player| player.name.as_str());
+        let message = format!("{name} left the game.");
+        for (client, _) in &self.clients {
+            let _ = client.outbox.send(ServerMessage::Ended {
+                message: message.clone(),
+            });
+        }
+    }
+
+    fn seat_of(&self, client_id: u32) -> Option<u8> {
+        self.clients
+            .iter()
+            .find(|(client, _)| client.id == client_id)
+            .and_then(|(_, seat)| *seat)
+    }
+
+    fn tell(&self, client_id: u32, message: &str) {
+        if let Some((client, _)) = self
+            .clients
+            .iter()
+            .find(|(client, _)| client.id == client_id)
+        {
+            let _ = client.outbox.send(ServerMessage::Error {
+                message: message.to_string(),
+            });
+        }
+    }
+
+    fn broadcast_state(&self) {
+        let view = GameView::new(self.id, &self.game, self.open);
+        for (client, _) in &self.clients {
+            let _ = client.outbox.send(ServerMessage::State(view.clone()));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+/// The biggest request body we accept. Nothing we are sent should come close.
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/// An HTTP request from a browser. We only keep what we need to decide how to answer it.
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads the request line, the headers and the body, and leaves anything after them in `reader`
+    pub fn read(reader: &mut BufReader<TcpStream>) -> io::Result<Request> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Not an HTTP request");
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let (method, path) = (method.to_string(), path.to_string());
+
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        let mut request = Request {
+            method,
+            path,
+            headers,
+            body: Vec::new(),
+        };
+
+        let length = match request.header("content-length") {
+            Some(length) => length.parse().map_err(|_| invalid())?,
+            None => 0,
+        };
+        if length > MAX_BODY_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The request body is too long",
+            ));
+        }
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body)?;
+        Ok(request)
+    }
+
+    /// Looks up a header. Header names are not case sensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the browser wants to turn this connection into a WebSocket
+    pub fn is_websocket_upgrade(&self) -> bool {
+        self.header("upgrade")
+            .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+/// Answers a request, and closes the connection afterwards
+pub fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
@@ -0,0 +1,157 @@
+use crate::board::{Board, CellState};
+use crate::controller::InputType;
+use crate::gamestate::{GameOutcome, GameState};
+use serde::{Deserialize, Serialize};
+
+/// A message from a browser, e.g. `{"type": "coord", "index": 4}`
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Starts a game, and takes the first seat in it. Without an opponent, the second seat is left for another browser.
+    Create {
+        width: usize,
+        height: usize,
+        win_length: usize,
+        /// An AI strategy like `"minimax"` or `"mcts:easy"`
+        #[serde(default)]
+        opponent: Option<String>,
+    },
+    Join {
+        game: u32,
+    },
+    Watch {
+        game: u32,
+    },
+    // The same inputs a player at the keyboard has
+    Coord {
+        index: usize,
+    },
+    Restart,
+    Exit,
+}
+
+impl ClientMessage {
+    /// The move or command this message stands for, if it is one
+    pub fn input(&self) -> Option<InputType> {
+        match self {
+            ClientMessage::Coord { index } => Some(InputType::Coord(*index)),
+            ClientMessage::Restart => Some(InputType::Restart),
+            ClientMessage::Exit => Some(InputType::Exit),
+            _ => None,
+        }
+    }
+}
+
+/// A message to a browser, e.g. `{"type": "error", "message": "..."}`
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The browser is now in `game`, playing `seat`, or watching if there is no seat
+    Entered {
+        game: u32,
+        seat: Option<u8>,
+    },
+    /// Sent to everyone in a game whenever it changes
+    State(GameView),
+    Error {
+        message: String,
+    },
+    /// The game is over for good, e.g. because one of the players left
+    Ended {
+        message: String,
+    },
+}
+
+/// Everything a browser needs to draw a game
+#[derive(Serialize, Clone, Debug)]
+pub struct GameView {
+    pub game: u32,
+    pub width: usize,
+    pub height: usize,
+    pub win_length: usize,
+    /// Row by row, from the top left, like the cell numbers players type
+    pub cells: Vec<Cell>,
+    pub players: Vec<PlayerView>,
+    /// The seat whose turn it is
+    pub to_move: u8,
+    /// Whether the game is still waiting for a second player
+    pub open: bool,
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlayerView {
+    pub seat: u8,
+    pub name: String,
+    pub symbol: char,
+}
+
+/// A cell on the board, as `"empty"` or `{"player": 1}`
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Cell {
+    Empty,
+    Player(u8),
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Outcome {
+    Winner { seat: u8 },
+    Draw,
+}
+
+impl From<CellState> for Cell {
+    fn from(cell: CellState) -> Cell {
+        match cell {
+            CellState::Empty => Cell::Empty,
+            CellState::Player(piece) => Cell::Player(*piece),
+        }
+    }
+}
+
+impl From<GameOutcome> for Outcome {
+    fn from(outcome: GameOutcome) -> Outcome {
+        match outcome {
+            GameOutcome::Winner(piece) => Outcome::Winner { seat: *piece },
+            GameOutcome::Draw => Outcome::Draw,
+        }
+    }
+}
+
+impl GameView {
+    pub fn new(game: u32, game_state: &GameState, open: bool) -> GameView {
+        GameView {
+            game,
+            width: game_state.board().width(),
+            height: game_state.board().height(),
+            win_length: game_state.referee().win_length(),
+            cells: cells(game_state.board()),
+            players: game_state
+                .players()
+                .iter()
+                .map(|player| PlayerView {
+                    seat: *player.player_piece,
+                    name: player.name.clone(),
+                    symbol: player.symbol,
+                })
+                .collect(),
+            to_move: *game_state.piece_to_move(),
+            open,
+            outcome: game_state.outcome().map(Outcome::from),
+        }
+    }
+}
+
+fn cells(board: &Board) -> Vec<Cell> {
+    let (width, height) = (board.width() as isize, board.height() as isize);
+    (0..height)
+        .flat_map(|row| (0..width).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            board
+                .get_cell(row, col)
+                .expect("Every cell within the width and height is on the board")
+                .into()
+        })
+        .collect()
+}
@@ -0,0 +1,159 @@
+//! Playing in a web browser, with `--web PORT`.
+//!
+//! The page at `http://localhost:<port>/` is a small game client. It talks to the game over a WebSocket at `/ws`,
+//! one JSON object per message, each with a `type`. A browser can send:
+//!
+//! ```text
+//! {"type": "create", "width": 3, "height": 3, "win_length": 3, "opponent": "mcts:easy"}
+//!                                  Starts a game, and takes the first seat. Leave out the opponent to play against
+//!                                  another browser, which joins with the game's id.
+//! {"type": "join", "game": 1}      Takes the free seat in game 1
+//! {"type": "watch", "game": 1}     Watches game 1 without playing in it
+//! {"type": "coord", "index": 4}    Places a piece on cell 4, counting from the top left like `help` does
+//! {"type": "restart"}              Starts the game over
+//! {"type": "exit"}                 Leaves the game, which ends it for everyone
+//! ```
+//!
+//! The last three are the same inputs a player at the keyboard has (see `InputType`). The game answers with:
+//!
+//! ```text
+//! {"type": "entered", "game": 1, "seat": 1}
+//!                                  The browser is in game 1, in seat 1. The seat is null for spectators.
+//! {"type": "state", "game": 1, "width": 3, "height": 3, "win_length": 3, "cells": ["empty", {"player": 1}, ...],
+//!  "players": [{"seat": 1, "name": "Player 1", "symbol": "x"}, ...], "to_move": 2, "open": false,
+//!  "outcome": null}                Sent to everyone in the game whenever it changes. The outcome is
+//!                                  {"type": "winner", "seat": 1} or {"type": "draw"} once the game is over.
+//! {"type": "error", "message": "It's not your turn."}
+//! {"type": "ended", "message": "Player 2 left the game."}
+//! ```
+//!
+//! Every move is checked with `Board::is_valid_move` before it is made, so a browser can't cheat by sending its own JSON.
+//!
+//! # Correspondence games
+//!
+//! Games that go on for days, with one move at a time, are played through a REST API instead. They are stored in the
+//! games directory, one JSON file each, so they survive restarts. A file that can't be read is moved aside with
+//! `.broken` added to its name, and the other games go on as usual.
+//!
+//! ```text
+//! POST /games                {"width": 3, "height": 3, "win_length": 3,
+//!                             "players": [{"name": "Ann", "symbol": "x"}, {"name": "Bob", "symbol": "o"}]}
+//!                            Starts a game, and answers with {"game": <state>, "tokens": ["...", "..."]}. Each player
+//!                            gets the token for their seat, and nobody else should see it.
+//! GET /games/{id}            The game's state, just like the "state" message above
+//! POST /games/{id}/moves     {"token": "...", "index": 4}
+//!                            Plays a move for the player with the token, and answers with the new state
+//! GET /games/{id}/history    {"game": 1, "moves": [{"seat": 1, "index": 4, "played_at": 1760000000}, ...]}
+//!                            Every move so far, with the time it was played in seconds since the Unix epoch
+//! ```
+//!
+//! Anything that goes wrong is answered with an error status and {"message": "..."}.
+
+pub mod correspondence;
+pub mod game;
+pub mod http;
+pub mod json;
+pub mod rest;
+pub mod socket;
+
+use crate::web::correspondence::Archive;
+use crate::web::game::Games;
+use crate::web::http::Request;
+use std::fmt;
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The game client, built into the binary so there is nothing else to install
+const CLIENT_PAGE: &str = include_str!("client.html");
+
+#[derive(Debug)]
+pub enum WebError {
+    Io(io::Error),
+    // Boxed, as it is much bigger than the other errors
+    WebSocket(Box<tungstenite::Error>),
+}
+
+impl fmt::Display for WebError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebError::Io(e) => write!(f, "Network error: {e}"),
+            WebError::WebSocket(e) => write!(f, "WebSocket error: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for WebError {
+    fn from(e: io::Error) -> WebError {
+        WebError::Io(e)
+    }
+}
+
+impl From<tungstenite::Error> for WebError {
+    fn from(e: tungstenite::Error) -> WebError {
+        WebError::WebSocket(Box::new(e))
+    }
+}
+
+/// Serves the game client, the WebSocket and the REST API on `port` until the program is stopped. Only this computer
+/// can connect.
+///
+/// Correspondence games are kept in `games_dir`.
+pub fn run(port: u16, games_dir: &Path) -> Result<(), WebError> {
+    let archive = Archive::open(games_dir)?;
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Open http://localhost:{port}/ in your browser to play.");
+    serve(listener, archive)
+}
+
+/// Serves the game client, the WebSocket and the REST API to whoever connects to `listener`, with the correspondence
+/// games in `archive`
+pub fn serve(listener: TcpListener, archive: Archive) -> Result<(), WebError> {
+    let archive = Arc::new(Mutex::new(archive));
+    let games = Arc::new(Mutex::new(Games::default()));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let games = Arc::clone(&games);
+        let archive = Arc::clone(&archive);
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, games, &archive) {
+                println!("A browser connection failed: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(
+    mut stream: TcpStream,
+    games: Arc<Mutex<Games>>,
+    archive: &Mutex<Archive>,
+) -> Result<(), WebError> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = Request::read(&mut reader)?;
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/ws") if request.is_websocket_upgrade() => {
+            let leftover = reader.buffer().to_vec();
+            socket::serve(stream, leftover, &request, games)
+        }
+        (_, path) if path == "/games" || path.starts_with("/games/") => {
+            Ok(rest::handle(&mut stream, &request, archive)?)
+        }
+        ("GET", "/" | "/index.html") => Ok(http::respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            CLIENT_PAGE.as_bytes(),
+        )?),
+        _ => Ok(http::respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"Not found",
+        )?),
+    }
+}
@@ -0,0 +1,292 @@
+use crate::network;
+use crate::web::correspondence::{Archive, MoveError, PlayedMove};
+use crate::web::http::{self, Request};
+use crate::web::json::GameView;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::TcpStream;
+use std::sync::Mutex;
+
+#[derive(Deserialize)]
+struct NewGame {
+    width: usize,
+    height: usize,
+    win_length: usize,
+    players: [NewPlayer; 2],
+}
+
+#[derive(Deserialize)]
+struct NewPlayer {
+    name: String,
+    symbol: char,
+}
+
+#[derive(Deserialize)]
+struct NewMove {
+    token: String,
+    index: usize,
+}
+
+/// The answer to creating a game. The tokens are only ever handed out here, one for each seat.
+#[derive(Serialize)]
+struct Created {
+    game: GameView,
+    tokens: [String; 2],
+}
+
+#[derive(Serialize)]
+struct History<'a> {
+    game: u32,
+    moves: &'a [PlayedMove],
+}
+
+#[derive(Serialize)]
+struct Failure<'a> {
+    message: &'a str,
+}
+
+/// Answers a request for anything under `/games`
+pub fn handle(
+    stream: &mut TcpStream,
+    request: &Request,
+    archive: &Mutex<Archive>,
+) -> io::Result<()> {
+    let mut archive = archive.lock().expect("The archive lock is never poisoned");
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let id = segments.get(1).map(|id| id.parse::<u32>());
+    match (request.method.as_str(), segments.as_slice(), id) {
+        ("POST", ["games"], _) => create(stream, request, &mut archive),
+        (_, [_, _, ..], Some(Err(_))) => fail(stream, "404 Not Found", "There is no such game."),
+        (_, [_, _, ..], Some(Ok(id))) if archive.get(id).is_none() => {
+            fail(stream, "404 Not Found", &format!("There is no game {id}."))
+        }
+        ("GET", [_, _], Some(Ok(id))) => {
+            let game = archive.get(id).expect("The game was looked up above");
+            send_json(
+                stream,
+                "200 OK",
+                &GameView::new(id, &game.game_state(), false),
+            )
+        }
+        ("GET", [_, _, "history"], Some(Ok(id))) => {
+            let game = archive.get(id).expect("The game was looked up above");
+            let history = History {
+                game: id,
+                moves: &game.moves,
+            };
+            send_json(stream, "200 OK", &history)
+        }
+        ("POST", [_, _, "moves"], Some(Ok(id))) => play(stream, request, &mut archive, id),
+        (_, ["games"] | [_, _] | [_, _, "history" | "moves"], _) => fail(
+            stream,
+            "405 Method Not Allowed",
+            &format!("{} is not allowed here.", request.method),
+        ),
+        _ => fail(stream, "404 Not Found", "Not found"),
+    }
+}
+
+/// `POST /games`
+fn create(stream: &mut TcpStream, request: &Request, archive: &mut Archive) -> io::Result<()> {
+    let new_game: NewGame = match serde_json::from_slice(&request.body) {
+        Ok(new_game) => new_game,
+        Err(e) => {
+            return fail(
+                stream,
+                "400 Bad Request",
+                &format!("Could not understand that game: {e}"),
+            );
+        }
+    };
+    if network::check_rules(new_game.width, new_game.height, new_game.win_length).is_none() {
+        return fail(stream, "400 Bad Request", "We can't play with those rules.");
+    }
+    let [first, second] = new_game.players;
+    if first.symbol == second.symbol {
+        return fail(
+            stream,
+            "400 Bad Request",
+            "The players need different symbols.",
+        );
+    }
+    let created = archive.create(
+        new_game.width,
+        new_game.height,
+        new_game.win_length,
+        [(first.name, first.symbol), (second.name, second.symbol)],
+    );
+    match created {
+        Ok(game) => {
+            let created = Created {
+                game: GameView::new(game.id, &game.game_state(), false),
+                tokens: game
+                    .players
+                    .clone()
+                    .map(|correspondent| correspondent.token),
+            };
+            send_json(stream, "201 Created", &created)
+        }
+        Err(e) => fail(
+            stream,
+            "500 Internal Server Error",
+            &format!("Could not save the game: {e}"),
+        ),
+    }
+}
+
+/// `POST /games/{id}/moves`
+fn play(
+    stream: &mut TcpStream,
+    request: &Request,
+    archive: &mut Archive,
+    id: u32,
+) -> io::Result<()> {
+    let new_move: NewMove = match serde_json::from_slice(&request.body) {
+        Ok(new_move) => new_move,
+        Err(e) => {
+            return fail(
+                stream,
+                "400 Bad Request",
+                &format!("Could not understand that move: {e}"),
+            );
+        }
+    };
+    match archive.play(id, &new_move.token, new_move.index) {
+        Ok(game) => send_json(
+            stream,
+            "200 OK",
+            &GameView::new(id, &game.game_state(), false),
+        ),
+        Err(MoveError::NotAPlayer) => {
+            fail(stream, "403 Forbidden", "That token is not for this game.")
+        }
+        Err(MoveError::OutOfTurn(reason)) => fail(stream, "409 Conflict", &reason),
+        Err(MoveError::InvalidMove(reason)) => fail(stream, "400 Bad Request", &reason),
+        Err(MoveError::Io(e)) => fail(
+            stream,
+            "500 Internal Server Error",
+            &format!("Could not save the move: {e}"),
+        ),
+    }
+}
+
+fn send_json(stream: &mut TcpStream, status: &str, body: &impl Serialize) -> io::Result<()> {
+    let json = serde_json::to_vec(body).expect("Our answers can always be written as JSON");
+    http::respond(stream, status, "application/json", &json)
+}
+
+fn fail(stream: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+    send_json(stream, status, &Failure { message })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::web::{self, correspondence::Archive};
+    use serde_json::{Value, json};
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::Path;
+    use std::process;
+    use std::thread;
+
+    /// Serves the games in `dir` on a free port on localhost, and returns the address
+    fn start_server(dir: &Path) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let archive = Archive::open(dir).unwrap();
+        thread::spawn(move || web::serve(listener, archive));
+        address
+    }
+
+    /// Sends a request, and returns the status and the JSON that came back
+    fn request(address: &str, method: &str, path: &str, body: &str) -> (String, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().trim_start_matches("HTTP/1.1 ");
+        (status.to_string(), serde_json::from_str(body).unwrap())
+    }
+
+    fn play(address: &str, token: &Value, index: usize) -> (String, Value) {
+        let body = json!({ "token": token, "index": index }).to_string();
+        request(address, "POST", "/games/1/moves", &body)
+    }
+
+    #[test]
+    fn play_a_game_through_a_restart() {
+        let dir = env::temp_dir().join(format!("tictactoe-rest-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let address = start_server(&dir);
+
+        let new_game = r#"{"width": 3, "height": 3, "win_length": 3,
+            "players": [{"name": "Ann", "symbol": "x"}, {"name": "Bob", "symbol": "y"}]}"#;
+        let (status, created) = request(&address, "POST", "/games", new_game);
+        assert_eq!(status, "201 Created");
+        assert_eq!(created["game"]["game"], 1);
+        let [ann, bob] = [&created["tokens"][0], &created["tokens"][1]];
+
+        let (status, state) = play(&address, ann, 4);
+        assert_eq!(status, "200 OK");
+        assert_eq!(state["cells"][4], json!({ "player": 1 }));
+        assert_eq!(state["to_move"], 2);
+
+        // Starting the server again over the same directory picks the game up where it was
+        let address = start_server(&dir);
+        assert_eq!(
+            request(&address, "GET", "/games/1", ""),
+            ("200 OK".to_string(), state)
+        );
+        let (status, history) = request(&address, "GET", "/games/1/history", "");
+        assert_eq!(status, "200 OK");
+        assert_eq!(history["moves"][0]["index"], 4);
+        assert_eq!(play(&address, bob, 0).0, "200 OK");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn mistakes_get_the_right_status() {
+        let dir = env::temp_dir().join(format!("tictactoe-rest-errors-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let address = start_server(&dir);
+        let new_game = r#"{"width": 3, "height": 3, "win_length": 3,
+            "players": [{"name": "Ann", "symbol": "x"}, {"name": "Bob", "symbol": "y"}]}"#;
+        let (_, created) = request(&address, "POST", "/games", new_game);
+        let [ann, bob] = [&created["tokens"][0], &created["tokens"][1]];
+
+        let (status, answer) = play(&address, bob, 4);
+        assert_eq!(status, "409 Conflict");
+        assert_eq!(answer["message"], "It's not your turn.");
+        assert_eq!(play(&address, ann, 4).0, "200 OK");
+        assert_eq!(play(&address, bob, 4).0, "400 Bad Request");
+        assert_eq!(play(&address, bob, 9).0, "400 Bad Request");
+        assert_eq!(play(&address, &json!("nobody"), 0).0, "403 Forbidden");
+        let moves = "/games/1/moves";
+        assert_eq!(request(&address, "POST", moves, "{").0, "400 Bad Request");
+        assert_eq!(request(&address, "GET", "/games/2", "").0, "404 Not Found");
+        assert_eq!(
+            request(&address, "DELETE", "/games/1", "").0,
+            "405 Method Not Allowed"
+        );
+
+        let same_symbols = new_game.replace(r#""symbol": "y""#, r#""symbol": "x""#);
+        assert_eq!(
+            request(&address, "POST", "/games", &same_symbols).0,
+            "400 Bad Request"
+        );
+        let too_long = new_game.replace(r#""win_length": 3"#, r#""win_length": 4"#);
+        assert_eq!(
+            request(&address, "POST", "/games", &too_long).0,
+            "400 Bad Request"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
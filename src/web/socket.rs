@@ -0,0 +1,312 @@
+use crate::cli;
+use crate::network;
+use crate::web::WebError;
+use crate::web::game::{self, Client, Command, Games};
+use crate::web::http::Request;
+use crate::web::json::{ClientMessage, ServerMessage};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tungstenite::error::ProtocolError;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// How long we wait for a message from the browser before passing on what the game has to say
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A browser's WebSocket connection, and the game it is in
+struct Session {
+    socket: WebSocket<TcpStream>,
+    client_id: u32,
+    outbox: Sender<ServerMessage>,
+    game: Option<Sender<Command>>,
+    games: Arc<Mutex<Games>>,
+}
+
+/// Turns the connection into a WebSocket, and passes JSON messages between the browser and its game until it closes.
+///
+/// `leftover` is whatever the browser sent after the request headers.
+pub fn serve(
+    mut stream: TcpStream,
+    leftover: Vec<u8>,
+    request: &Request,
+    games: Arc<Mutex<Games>>,
+) -> Result<(), WebError> {
+    let key = request.header("sec-websocket-key").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The WebSocket request has no key",
+        )
+    })?;
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    )?;
+    stream.flush()?;
+    // The game may have something to say while the browser is quiet, so we can't wait on the browser forever
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (outbox, inbox) = mpsc::channel();
+    let client_id = games
+        .lock()
+        .expect("The games lock is never poisoned")
+        .new_client_id();
+    let mut session = Session {
+        socket: WebSocket::from_partially_read(stream, leftover, Role::Server, None),
+        client_id,
+        outbox,
+        game: None,
+        games,
+    };
+    let result = loop {
+        if let Err(e) = inbox
+            .try_iter()
+            .try_for_each(|message| session.send(&message))
+        {
+            break Err(e);
+        }
+        match session.socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(message) => session.handle(message),
+                Err(e) => session.tell(&format!("Could not understand that message: {e}")),
+            },
+            // Browsers often just drop the connection when a tab is closed, which is fine by us
+            Ok(Message::Close(_))
+            | Err(
+                tungstenite::Error::ConnectionClosed
+                | tungstenite::Error::AlreadyClosed
+                | tungstenite::Error::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+            ) => {
+                break Ok(());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    session.leave_game();
+    result
+}
+
+impl Session {
+    fn handle(&mut self, message: ClientMessage) {
+        if let Some(input) = message.input() {
+            let Some(game) = &self.game else {
+                self.tell("Create or join a game first.");
+                return;
+            };
+            let command = Command::Input {
+                client_id: self.client_id,
+                input,
+            };
+            if game.send(command).is_err() {
+                self.tell("The game is over.");
+            }
+            return;
+        }
+
+        let (game, play) = match message {
+            ClientMessage::Create {
+                width,
+                height,
+                win_length,
+                opponent,
+            } => {
+                let Some((board, win_length)) = network::check_rules(width, height, win_length)
+                else {
+                    self.tell("We can't play with those rules.");
+                    return;
+                };
+                let opponent = match opponent {
+                    Some(name) => match cli::parse_contender(&name) {
+                        Some(contender) => Some(contender),
+                        None => {
+                            self.tell(&format!("{name} is not an AI we know."));
+                            return;
+                        }
+                    },
+                    None => None,
+                };
+                let (_, game) = game::create(&self.games, board, win_length, opponent);
+                (game, true)
+            }
+            ClientMessage::Join { game: id } | ClientMessage::Watch { game: id } => {
+                let found = self
+                    .games
+                    .lock()
+                    .expect("The games lock is never poisoned")
+                    .find(id);
+                let Some(game) = found else {
+                    self.tell(&format!("There is no game {id}."));
+                    return;
+                };
+                (game, matches!(message, ClientMessage::Join { .. }))
+            }
+            _ => return,
+        };
+        self.leave_game();
+        let client = Client {
+            id: self.client_id,
+            outbox: self.outbox.clone(),
+        };
+        if game.send(Command::Enter { client, play }).is_ok() {
+            self.game = Some(game);
+        } else {
+            self.tell("The game is over.");
+        }
+    }
+
+    fn leave_game(&mut self) {
+        if let Some(game) = self.game.take() {
+            let _ = game.send(Command::Leave {
+                client_id: self.client_id,
+            });
+        }
+    }
+
+    /// Sends an error to the browser. It comes through the outbox, so it arrives in order with everything else.
+    fn tell(&self, message: &str) {
+        let _ = self.outbox.send(ServerMessage::Error {
+            message: message.to_string(),
+        });
+    }
+
+    fn send(&mut self, message: &ServerMessage) -> Result<(), WebError> {
+        let json =
+            serde_json::to_string(message).expect("Server messages can always be written as JSON");
+        self.socket.send(Message::Text(json))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::web::{self, correspondence::Archive};
+    use serde_json::{Value, json};
+    use std::env;
+    use std::net::{TcpListener, TcpStream};
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+    use tungstenite::{Message, WebSocket};
+
+    struct Browser(WebSocket<TcpStream>);
+
+    impl Browser {
+        fn send(&mut self, text: &str) {
+            self.0.send(Message::Text(text.to_string())).unwrap();
+        }
+
+        /// The next JSON message from the game, waiting a few seconds at most
+        fn receive(&mut self) -> Value {
+            loop {
+                if let Message::Text(text) = self.0.read().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        }
+
+        /// The next message, which should be an error
+        fn error(&mut self) -> Value {
+            let message = self.receive();
+            assert_eq!(message["type"], "error", "{message}");
+            message["message"].clone()
+        }
+
+        /// The next message, which should be the state of the game
+        fn state(&mut self) -> Value {
+            let message = self.receive();
+            assert_eq!(message["type"], "state", "{message}");
+            message
+        }
+    }
+
+    /// Starts a web server on a free port on localhost, and returns the address
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let dir = env::temp_dir().join(format!("tictactoe-socket-{}", process::id()));
+        let archive = Archive::open(&dir).unwrap();
+        thread::spawn(move || web::serve(listener, archive));
+        address
+    }
+
+    fn connect(address: &str) -> Browser {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let (socket, _) = tungstenite::client(format!("ws://{address}/ws"), stream).unwrap();
+        Browser(socket)
+    }
+
+    /// Two browsers in a new 3x3 game, with the one who created it first
+    fn start_game(address: &str) -> (Browser, Browser) {
+        let mut ann = connect(address);
+        ann.send(r#"{"type": "create", "width": 3, "height": 3, "win_length": 3}"#);
+        let entered = ann.receive();
+        assert_eq!(entered["seat"], 1);
+        ann.state();
+
+        let mut bob = connect(address);
+        bob.send(&json!({ "type": "join", "game": entered["game"] }).to_string());
+        assert_eq!(bob.receive()["seat"], 2);
+        assert_eq!(bob.state()["open"], false);
+        assert_eq!(ann.state()["open"], false);
+        (ann, bob)
+    }
+
+    #[test]
+    fn moves_go_to_both_players() {
+        let address = start_server();
+        let (mut ann, mut bob) = start_game(&address);
+        ann.send(r#"{"type": "coord", "index": 4}"#);
+        for browser in [&mut ann, &mut bob] {
+            let state = browser.state();
+            assert_eq!(state["cells"][4], json!({ "player": 1 }));
+            assert_eq!(state["to_move"], 2);
+        }
+    }
+
+    #[test]
+    fn mistakes_are_answered_with_errors() {
+        let address = start_server();
+        let mut lost = connect(&address);
+        lost.send(r#"{"type": "coord", "index": 4}"#);
+        assert_eq!(lost.error(), "Create or join a game first.");
+
+        let (mut ann, mut bob) = start_game(&address);
+        bob.send(r#"{"type": "coord", "index": 4}"#);
+        assert_eq!(bob.error(), "It's not your turn.");
+        ann.send(r#"{"type": "coord", "index": 4}"#);
+        ann.state();
+        bob.state();
+        bob.send(r#"{"type": "coord", "index": 4}"#);
+        assert_eq!(bob.error(), "4 is not a legal move");
+        bob.send(r#"{"type": "coord", "index": 9}"#);
+        assert_eq!(bob.error(), "Move 9 would be out of bounds");
+        bob.send(r#"{"type": "coord", "index": "#);
+        let error = bob.error();
+        assert!(
+            error
+                .as_str()
+                .unwrap()
+                .starts_with("Could not understand that message")
+        );
+        bob.send(r#"{"type": "fly", "to": "the moon"}"#);
+        bob.error();
+
+        // None of that cost Bob the connection, or the turn
+        bob.send(r#"{"type": "coord", "index": 0}"#);
+        assert_eq!(bob.state()["cells"][0], json!({ "player": 2 }));
+        assert_eq!(ann.state()["to_move"], 1);
+    }
+}